@@ -0,0 +1,207 @@
+//! Registry of the NFT collections the market trades.
+//!
+//! Every collection is registered by the owner through `set_support_token` with the metadata the
+//! market needs to trade it, so entry points no longer rely on caller supplied settings.
+//!
+//! Collections supported before the registry existed are only in `TOKEN_CONTRACT_LIST`. They get a
+//! placeholder record on migration, which the owner completes by registering them again.
+//!
+//! A collection can also be put in seller allowlist mode, then only sellers approved by its
+//! creator or the market owner can list its tokens.
+use alloc::{string::String, vec::Vec};
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    bytesrepr::{self, FromBytes, ToBytes},
+    CLType, CLTyped, Key, U256,
+};
+use core::convert::TryFrom;
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{
+    get_approved_seller_key, get_dictionary_value_from_key, get_key, get_optional_named_arg,
+    get_stored_value_with_user_errors, make_dictionary_item_key_for_key,
+    write_dictionary_value_from_key, NFTIdentifierMode,
+};
+use crate::nft::NFTStandard;
+
+#[derive(Clone)]
+pub(crate) struct Collection {
+    pub name: String,
+    pub identifier_mode: u8, // CEP-78 NFTIdentifierMode
+    pub creator: Key,        // receives the royalty
+    pub royalty_fee: U256,   // in basis points
+    pub added_at: u64,       // block time in milliseconds
+    pub enabled: bool,
+    pub standard: u8,        // NFTStandard
+}
+
+impl Collection {
+    pub(crate) fn identifier_mode(&self) -> NFTIdentifierMode {
+        NFTIdentifierMode::try_from(self.identifier_mode).unwrap_or_revert()
+    }
+
+    pub(crate) fn standard(&self) -> NFTStandard {
+        NFTStandard::try_from(self.standard).unwrap_or_revert()
+    }
+}
+
+impl CLTyped for Collection {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for Collection {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.name.to_bytes()?);
+        result.append(&mut self.identifier_mode.to_bytes()?);
+        result.append(&mut self.creator.to_bytes()?);
+        result.append(&mut self.royalty_fee.to_bytes()?);
+        result.append(&mut self.added_at.to_bytes()?);
+        result.append(&mut self.enabled.to_bytes()?);
+        result.append(&mut self.standard.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.name.serialized_length()
+            + self.identifier_mode.serialized_length()
+            + self.creator.serialized_length()
+            + self.royalty_fee.serialized_length()
+            + self.added_at.serialized_length()
+            + self.enabled.serialized_length()
+            + self.standard.serialized_length()
+    }
+}
+
+impl FromBytes for Collection {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (name, remainder) = String::from_bytes(bytes)?;
+        let (identifier_mode, remainder) = u8::from_bytes(remainder)?;
+        let (creator, remainder) = Key::from_bytes(remainder)?;
+        let (royalty_fee, remainder) = U256::from_bytes(remainder)?;
+        let (added_at, remainder) = u64::from_bytes(remainder)?;
+        let (enabled, remainder) = bool::from_bytes(remainder)?;
+        let (standard, remainder) = u8::from_bytes(remainder)?;
+        Ok((
+            Collection {
+                name,
+                identifier_mode,
+                creator,
+                royalty_fee,
+                added_at,
+                enabled,
+                standard,
+            },
+            remainder,
+        ))
+    }
+}
+
+pub(crate) fn read_collection(nft_contract_hash: &Key) -> Option<Collection> {
+    let collection_key = make_dictionary_item_key_for_key(*nft_contract_hash);
+    get_dictionary_value_from_key::<Collection>(COLLECTIONS, &collection_key)
+}
+
+/// Registered collection, reverts if the collection was never registered.
+pub(crate) fn get_collection(nft_contract_hash: &Key) -> Collection {
+    read_collection(nft_contract_hash).unwrap_or_revert_with(Error::UnsupportedToken)
+}
+
+/// Registered collection that is currently enabled for trading.
+pub(crate) fn get_enabled_collection(nft_contract_hash: &Key) -> Collection {
+    let collection = get_collection(nft_contract_hash);
+    if !collection.enabled {
+        runtime::revert(Error::UnsupportedToken);
+    }
+    collection
+}
+
+/// Collection an exit path settles against. Collections traded before the registry and not
+/// migrated yet take the identifier mode from the caller, as the market did then, so escrow can
+/// always be taken back.
+pub(crate) fn get_collection_for_exit(nft_contract_hash: &Key) -> Collection {
+    read_collection(nft_contract_hash).unwrap_or_else(|| {
+        let identifier_mode: u8 = get_optional_named_arg(ARG_IDENTIFIER_MODE)
+            .unwrap_or(NFTIdentifierMode::Ordinal as u8);
+        legacy_collection(identifier_mode)
+    })
+}
+
+pub(crate) fn write_collection(nft_contract_hash: &Key, collection: Collection) {
+    let collection_key = make_dictionary_item_key_for_key(*nft_contract_hash);
+    write_dictionary_value_from_key(COLLECTIONS, &collection_key, collection);
+}
+
+/// Registers the collections supported before the registry, if they aren't yet. They trade as
+/// ordinal CEP-78 collections with the market wide royalty paid to the owner, until the owner
+/// registers them again with their own settings.
+pub(crate) fn register_legacy_collections() {
+    let token_list: Vec<Key> = get_key(TOKEN_CONTRACT_LIST).unwrap_or_default();
+    for nft_contract_hash in token_list {
+        if read_collection(&nft_contract_hash).is_none() {
+            write_collection(&nft_contract_hash, legacy_collection(NFTIdentifierMode::Ordinal as u8));
+        }
+    }
+}
+
+fn legacy_collection(identifier_mode: u8) -> Collection {
+    Collection {
+        name: String::new(),
+        identifier_mode,
+        creator: get_stored_value_with_user_errors(
+            CONTRACT_OWNER_KEY_NAME,
+            Error::MissingContractOwner,
+            Error::InvalidContractOwner,
+        ),
+        royalty_fee: get_stored_value_with_user_errors(
+            ROYALTY_FEE,
+            Error::MissingRoyaltyFee,
+            Error::InvalidRoyaltyFee,
+        ),
+        added_at: runtime::get_blocktime().into(),
+        enabled: true,
+        standard: NFTStandard::CEP78 as u8,
+    }
+}
+
+/// Creates the dictionaries backing the seller allowlists, if they don't exist yet.
+pub(crate) fn create_seller_allowlist_dictionaries() {
+    for dictionary_name in [SELLER_ALLOWLIST_MODES, APPROVED_SELLERS].iter() {
+        if runtime::get_key(dictionary_name).is_none() {
+            storage::new_dictionary(dictionary_name)
+                .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+        }
+    }
+}
+
+pub(crate) fn is_seller_allowlist_enabled(nft_contract_hash: &Key) -> bool {
+    let mode_key = make_dictionary_item_key_for_key(*nft_contract_hash);
+    get_dictionary_value_from_key::<bool>(SELLER_ALLOWLIST_MODES, &mode_key).unwrap_or_default()
+}
+
+pub(crate) fn set_seller_allowlist_enabled(nft_contract_hash: &Key, enabled: bool) {
+    let mode_key = make_dictionary_item_key_for_key(*nft_contract_hash);
+    write_dictionary_value_from_key(SELLER_ALLOWLIST_MODES, &mode_key, enabled);
+}
+
+pub(crate) fn is_approved_seller(nft_contract_hash: &Key, seller: &Key) -> bool {
+    let approved_seller_key = get_approved_seller_key(nft_contract_hash, seller);
+    get_dictionary_value_from_key::<bool>(APPROVED_SELLERS, &approved_seller_key)
+        .unwrap_or_default()
+}
+
+pub(crate) fn set_approved_seller(nft_contract_hash: &Key, seller: &Key, approved: bool) {
+    let approved_seller_key = get_approved_seller_key(nft_contract_hash, seller);
+    write_dictionary_value_from_key(APPROVED_SELLERS, &approved_seller_key, approved);
+}
+
+/// Whether `seller` may list tokens of the collection right now.
+pub(crate) fn can_list(nft_contract_hash: &Key, seller: &Key) -> bool {
+    !is_seller_allowlist_enabled(nft_contract_hash) || is_approved_seller(nft_contract_hash, seller)
+}
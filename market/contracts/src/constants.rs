@@ -220,3 +220,78 @@ pub const ARG_ROYALTY_FEE : &str = "royalty_fee";
 pub const ROYALTY_FEE : &str = "royalty_fee";
 pub const ARG_IS_ROYALTY : &str = "is_royalty";
 pub const IS_ROYALTY : &str = "is_royalty";
+pub const ARG_REFERRER : &str = "referrer";
+pub const ARG_REFERRAL_SHARE : &str = "referral_share";
+pub const REFERRAL_SHARE : &str = "referral_share";
+pub const SET_REFERRAL_SHARE_ENTRY_POINT_NAME : &str = "set_referral_share";
+pub const ARG_STAKING_CONTRACT : &str = "staking_contract";
+pub const ARG_STAKING_POOL_ID : &str = "staking_pool_id";
+pub const ARG_DISCOUNT_THRESHOLDS : &str = "discount_thresholds";
+pub const ARG_DISCOUNT_RATES : &str = "discount_rates";
+pub const ARG_POOL_ID : &str = "pool_id";
+pub const ARG_USER : &str = "user";
+pub const STAKING_CONTRACT : &str = "staking_contract";
+pub const STAKING_POOL_ID : &str = "staking_pool_id";
+pub const DISCOUNT_THRESHOLDS : &str = "discount_thresholds";
+pub const DISCOUNT_RATES : &str = "discount_rates";
+pub const SET_FEE_DISCOUNT_ENTRY_POINT_NAME : &str = "set_fee_discount";
+pub const GET_LOCKED_STAKES_ENTRY_POINT_NAME : &str = "get_locked_stakes";
+pub const ARG_CURRENCY : &str = "currency";
+pub const ARG_ACCOUNT : &str = "account";
+pub const CLAIMABLE_BALANCES : &str = "claimable_balances";
+pub const WITHDRAW_ENTRY_POINT_NAME : &str = "withdraw";
+pub const GET_CLAIMABLE_BALANCE_ENTRY_POINT_NAME : &str = "get_claimable_balance";
+pub const ARG_BUYER_FEE : &str = "buyer_fee";
+pub const ARG_SELLER_FEE : &str = "seller_fee";
+pub const ARG_TOKEN_MARKET_KEYS : &str = "token_market_keys";
+pub const BUYER_FEE : &str = "buyer_fee";
+pub const SELLER_FEE : &str = "seller_fee";
+pub const FEE_VERSION : &str = "fee_version";
+pub const MIGRATE_FEE_MODEL_ENTRY_POINT_NAME : &str = "migrate_fee_model";
+pub const ARG_PRICE : &str = "price";
+pub const GET_SALE_QUOTE_ENTRY_POINT_NAME : &str = "get_sale_quote";
+pub const GET_LISTING_ENTRY_POINT_NAME : &str = "get_listing";
+pub const GET_BID_ENTRY_POINT_NAME : &str = "get_bid";
+pub const MIGRATE_STORAGE_ENTRY_POINT_NAME : &str = "migrate_storage";
+pub const INDEX_SIZES : &str = "index_sizes";
+pub const INDEX_ENTRIES : &str = "index_entries";
+pub const INDEX_POSITIONS : &str = "index_positions";
+pub const LISTINGS_BY_COLLECTION : &str = "listings_by_collection";
+pub const LISTINGS_BY_SELLER : &str = "listings_by_seller";
+pub const BIDS_BY_BIDDER : &str = "bids_by_bidder";
+pub const ARG_SELLER : &str = "seller";
+pub const ARG_BIDDER : &str = "bidder";
+pub const ARG_OFFSET : &str = "offset";
+pub const ARG_LIMIT : &str = "limit";
+pub const GET_LISTINGS_BY_COLLECTION_ENTRY_POINT_NAME : &str = "get_listings_by_collection";
+pub const GET_LISTINGS_BY_SELLER_ENTRY_POINT_NAME : &str = "get_listings_by_seller";
+pub const GET_BIDS_BY_BIDDER_ENTRY_POINT_NAME : &str = "get_bids_by_bidder";
+pub const COLLECTIONS : &str = "collections";
+pub const ARG_CREATOR : &str = "creator";
+pub const GET_COLLECTIONS_ENTRY_POINT_NAME : &str = "get_collections";
+pub const ARG_NFT_STANDARD : &str = "nft_standard";
+pub const ARG_SENDER : &str = "sender";
+pub const ARG_RECIPIENT : &str = "recipient";
+pub const COLLECTION_STATS : &str = "collection_stats";
+pub const TOKEN_LAST_SALE : &str = "token_last_sale";
+pub const GET_COLLECTION_STATS_ENTRY_POINT_NAME : &str = "get_collection_stats";
+pub const GET_TOKEN_LAST_SALE_ENTRY_POINT_NAME : &str = "get_token_last_sale";
+pub const SELLER_ALLOWLIST_MODES : &str = "seller_allowlist_modes";
+pub const APPROVED_SELLERS : &str = "approved_sellers";
+pub const ARG_SELLER_ALLOWLIST : &str = "seller_allowlist";
+pub const ARG_SELLERS : &str = "sellers";
+pub const ARG_APPROVED : &str = "approved";
+pub const SET_SELLER_ALLOWLIST_MODE_ENTRY_POINT_NAME : &str = "set_seller_allowlist_mode";
+pub const SET_APPROVED_SELLERS_ENTRY_POINT_NAME : &str = "set_approved_sellers";
+pub const IS_APPROVED_SELLER_ENTRY_POINT_NAME : &str = "is_approved_seller";
+pub const MARKET_PAUSE : &str = "market_pause";
+pub const COLLECTION_PAUSES : &str = "collection_pauses";
+pub const ARG_PAUSE_FLAGS : &str = "pause_flags";
+pub const SET_PAUSE_ENTRY_POINT_NAME : &str = "set_pause";
+pub const GET_PAUSE_ENTRY_POINT_NAME : &str = "get_pause";
+pub const STORAGE_VERSION : &str = "storage_version";
+pub const MIGRATE_ENTRY_POINT_NAME : &str = "migrate";
+pub const ARG_INDEX_ENTRIES : &str = "index_entries";
+pub const ARG_USERS : &str = "users";
+pub const ARG_BUYER : &str = "buyer";
+pub const MIGRATE_GROUP : &str = "migrate";
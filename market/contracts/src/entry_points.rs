@@ -3,7 +3,8 @@ use alloc::{boxed::Box, string::String, vec};
 use crate::constants::*;
 
 use casper_types::{
-    CLType, CLTyped, EntryPoint, EntryPointAccess, EntryPointType, EntryPoints, Parameter, U256,
+    CLType, CLTyped, EntryPoint, EntryPointAccess, EntryPointType, EntryPoints, Group, Key,
+    Parameter, U256,
 };
 
 // fn request_bridge_nft() -> EntryPoint {
@@ -36,7 +37,11 @@ fn transfer_owner() -> EntryPoint {
 fn change_fee() -> EntryPoint {
     EntryPoint::new(
         String::from(CHANGE_FEE_ENTRY_POINT_NAME),
-        vec![Parameter::new(ARG_MARKET_FEE, CLType::U256)],
+        vec![
+            Parameter::new(ARG_BUYER_FEE, CLType::Option(Box::new(CLType::U256))),
+            Parameter::new(ARG_SELLER_FEE, CLType::Option(Box::new(CLType::U256))),
+            Parameter::new(ARG_MARKET_FEE, CLType::Option(Box::new(CLType::U256))),
+        ],
         CLType::Unit,
         EntryPointAccess::Public,
         EntryPointType::Contract,
@@ -108,6 +113,7 @@ fn bid() -> EntryPoint {
             Parameter::new(ARG_BIDDING_OFFER, CLType::U256),
             Parameter::new(ARG_TOKEN_ID, CLType::U64),
             Parameter::new(ARG_TOKEN_HASH, CLType::String),
+            Parameter::new(ARG_REFERRER, CLType::Option(Box::new(CLType::Key))),
         ],
         CLType::Unit,
         EntryPointAccess::Public,
@@ -122,6 +128,7 @@ fn increase_bid() -> EntryPoint {
             Parameter::new(ARG_NEW_OFFER, CLType::U256),
             Parameter::new(ARG_TOKEN_ID, CLType::U64),
             Parameter::new(ARG_TOKEN_HASH, CLType::String),
+            Parameter::new(ARG_REFERRER, CLType::Option(Box::new(CLType::Key))),
         ],
         CLType::Unit,
         EntryPointAccess::Public,
@@ -160,6 +167,298 @@ fn set_support_token() -> EntryPoint {
         vec![
             Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
             Parameter::new(ARG_NFT_ENABLED, CLType::Bool),
+            Parameter::new(ARG_COLLECTION_NAME, CLType::String),
+            Parameter::new(ARG_IDENTIFIER_MODE, CLType::U8),
+            Parameter::new(ARG_CREATOR, CLType::Key),
+            Parameter::new(ARG_ROYALTY_FEE, CLType::U256),
+            Parameter::new(ARG_NFT_STANDARD, CLType::U8),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_collections() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_COLLECTIONS_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_OFFSET, CLType::U64),
+            Parameter::new(ARG_LIMIT, CLType::U64),
+        ],
+        CLType::List(Box::new(CLType::Tuple2([
+            Box::new(CLType::Key),
+            Box::new(CLType::Any),
+        ]))),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_referral_share() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_REFERRAL_SHARE_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_REFERRAL_SHARE, CLType::U256)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_fee_discount() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_FEE_DISCOUNT_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_STAKING_CONTRACT, CLType::Key),
+            Parameter::new(ARG_STAKING_POOL_ID, CLType::U64),
+            Parameter::new(ARG_DISCOUNT_THRESHOLDS, CLType::List(Box::new(CLType::U256))),
+            Parameter::new(ARG_DISCOUNT_RATES, CLType::List(Box::new(CLType::U256))),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn withdraw() -> EntryPoint {
+    EntryPoint::new(
+        String::from(WITHDRAW_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_CURRENCY, CLType::Key)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+fn get_claimable_balance() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_CLAIMABLE_BALANCE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_ACCOUNT, CLType::Key),
+            Parameter::new(ARG_CURRENCY, CLType::Key),
+        ],
+        CLType::U256,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_listing() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_LISTING_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::U64),
+            Parameter::new(ARG_TOKEN_HASH, CLType::String),
+        ],
+        CLType::Option(Box::new(<(Key, U256, bool)>::cl_type())),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_bid() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_BID_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::U64),
+            Parameter::new(ARG_TOKEN_HASH, CLType::String),
+        ],
+        CLType::Option(Box::new(<(Key, U256, U256)>::cl_type())),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_sale_quote() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_SALE_QUOTE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::U64),
+            Parameter::new(ARG_TOKEN_HASH, CLType::String),
+            Parameter::new(ARG_PRICE, CLType::U256),
+            Parameter::new(ARG_BUYER, CLType::Option(Box::new(CLType::Key))),
+        ],
+        <((U256, U256), (Option<Key>, U256), U256)>::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn migrate_fee_model() -> EntryPoint {
+    EntryPoint::new(
+        String::from(MIGRATE_FEE_MODEL_ENTRY_POINT_NAME),
+        vec![Parameter::new(
+            ARG_TOKEN_MARKET_KEYS,
+            CLType::List(Box::new(CLType::String)),
+        )],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_listings_by_collection() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_LISTINGS_BY_COLLECTION_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_OFFSET, CLType::U64),
+            Parameter::new(ARG_LIMIT, CLType::U64),
+        ],
+        CLType::List(Box::new(CLType::Tuple2([
+            Box::new(CLType::Key),
+            Box::new(CLType::String),
+        ]))),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_listings_by_seller() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_LISTINGS_BY_SELLER_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_SELLER, CLType::Key),
+            Parameter::new(ARG_OFFSET, CLType::U64),
+            Parameter::new(ARG_LIMIT, CLType::U64),
+        ],
+        CLType::List(Box::new(CLType::Tuple2([
+            Box::new(CLType::Key),
+            Box::new(CLType::String),
+        ]))),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_bids_by_bidder() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_BIDS_BY_BIDDER_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_BIDDER, CLType::Key),
+            Parameter::new(ARG_OFFSET, CLType::U64),
+            Parameter::new(ARG_LIMIT, CLType::U64),
+        ],
+        CLType::List(Box::new(CLType::Tuple2([
+            Box::new(CLType::Key),
+            Box::new(CLType::String),
+        ]))),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_collection_stats() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_COLLECTION_STATS_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key)],
+        CLType::Any,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_token_last_sale() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_TOKEN_LAST_SALE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::U64),
+            Parameter::new(ARG_TOKEN_HASH, CLType::String),
+        ],
+        CLType::Option(Box::new(CLType::U256)),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_seller_allowlist_mode() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_SELLER_ALLOWLIST_MODE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_SELLER_ALLOWLIST, CLType::Bool),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_approved_sellers() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_APPROVED_SELLERS_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_SELLERS, CLType::List(Box::new(CLType::Key))),
+            Parameter::new(ARG_APPROVED, CLType::Bool),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn is_approved_seller() -> EntryPoint {
+    EntryPoint::new(
+        String::from(IS_APPROVED_SELLER_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_SELLER, CLType::Key),
+        ],
+        CLType::Bool,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_pause() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_PAUSE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key),
+            Parameter::new(ARG_PAUSE_FLAGS, CLType::U8),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_pause() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_PAUSE_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_NFT_CONTRACT_HASH, CLType::Key)],
+        CLType::U8,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn migrate() -> EntryPoint {
+    EntryPoint::new(
+        String::from(MIGRATE_ENTRY_POINT_NAME),
+        vec![Parameter::new(ARG_CONTRACT_HASH, CLType::Key)],
+        CLType::Unit,
+        EntryPointAccess::Groups(vec![Group::new(MIGRATE_GROUP)]),
+        EntryPointType::Contract,
+    )
+}
+
+fn migrate_storage() -> EntryPoint {
+    EntryPoint::new(
+        String::from(MIGRATE_STORAGE_ENTRY_POINT_NAME),
+        vec![
+            Parameter::new(ARG_TOKEN_MARKET_KEYS, CLType::List(Box::new(CLType::String))),
+            Parameter::new(
+                ARG_INDEX_ENTRIES,
+                CLType::Option(Box::new(CLType::List(Box::new(CLType::Tuple2([
+                    Box::new(CLType::Key),
+                    Box::new(CLType::String),
+                ]))))),
+            ),
         ],
         CLType::Unit,
         EntryPointAccess::Public,
@@ -175,7 +474,10 @@ fn init() -> EntryPoint {
             Parameter::new(ARG_CONTRACT_OWNER, CLType::Key),
             Parameter::new(ARG_MARKET_FEE_RECEIVER, CLType::Key),
             Parameter::new(ARG_WCSPR_CONTRACT, CLType::Key),
-            Parameter::new(ARG_MARKET_FEE, CLType::U256),
+            Parameter::new(ARG_BUYER_FEE, CLType::U256),
+            Parameter::new(ARG_SELLER_FEE, CLType::U256),
+            Parameter::new(ARG_ROYALTY_FEE, CLType::U256),
+            Parameter::new(ARG_IS_ROYALTY, CLType::Bool),
         ],
         CLType::Unit,
         EntryPointAccess::Public,
@@ -199,5 +501,26 @@ pub(crate) fn default() -> EntryPoints {
     entry_points.add_entry_point(revoke_bid());
     entry_points.add_entry_point(set_support_token());
     entry_points.add_entry_point(change_is_royalty());
+    entry_points.add_entry_point(set_referral_share());
+    entry_points.add_entry_point(set_fee_discount());
+    entry_points.add_entry_point(withdraw());
+    entry_points.add_entry_point(get_claimable_balance());
+    entry_points.add_entry_point(get_sale_quote());
+    entry_points.add_entry_point(get_listing());
+    entry_points.add_entry_point(get_bid());
+    entry_points.add_entry_point(migrate_fee_model());
+    entry_points.add_entry_point(migrate_storage());
+    entry_points.add_entry_point(get_listings_by_collection());
+    entry_points.add_entry_point(get_listings_by_seller());
+    entry_points.add_entry_point(get_bids_by_bidder());
+    entry_points.add_entry_point(get_collections());
+    entry_points.add_entry_point(get_collection_stats());
+    entry_points.add_entry_point(get_token_last_sale());
+    entry_points.add_entry_point(set_seller_allowlist_mode());
+    entry_points.add_entry_point(set_approved_sellers());
+    entry_points.add_entry_point(is_approved_seller());
+    entry_points.add_entry_point(set_pause());
+    entry_points.add_entry_point(get_pause());
+    entry_points.add_entry_point(migrate());
     entry_points
 }
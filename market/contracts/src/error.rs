@@ -52,6 +52,21 @@ pub enum Error {
     MissingIsRoyalty = 126,
     InvalidIsRoyalty = 127,
     SameIsRoyalty = 128,
+    ReferralShareTooHigh = 129,
+    InvalidReferrer = 130,
+    InvalidDiscountTiers = 131,
+    DiscountTooHigh = 132,
+    NothingToWithdraw = 133,
+    FailedToConvertToCLValue = 134,
+    InvalidNFTStandard = 135,
+    MissingTokenOwner = 136,
+    SellerNotApproved = 137,
+    InvalidCollectionAdmin = 138,
+    ListingPaused = 139,
+    BiddingPaused = 140,
+    SettlementPaused = 141,
+    InvalidPauseFlags = 142,
+    InvalidStakingContract = 143,
 }
 
 impl From<Error> for ApiError {
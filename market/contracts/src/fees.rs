@@ -0,0 +1,138 @@
+//! Fee model shared by every market entry point.
+//!
+//! Every rate is in basis points of the sale price. The buyer pays the price plus the buyer fee,
+//! the seller receives the price less the seller fee and the royalty.
+use alloc::vec::Vec;
+use casper_types::{
+    bytesrepr::{self, FromBytes, ToBytes},
+    CLType, CLTyped, Key, U256,
+};
+
+/// Basis points in 100%.
+pub const FEE_DIVISOR: u64 = 10000;
+/// Highest buyer or seller fee the owner can set, 2%.
+pub const MAX_MARKET_FEE: u64 = 200;
+/// Highest royalty the owner can set, 1%.
+pub const MAX_ROYALTY_FEE: u64 = 100;
+/// Fee model version that token markets written by this contract follow.
+pub const CURRENT_FEE_VERSION: u8 = 1;
+
+/// How a sale price is split between the market, the royalty recipient and the seller.
+#[derive(Clone, Copy)]
+pub(crate) struct FeeBreakdown {
+    pub price: U256,
+    pub buyer_fee: U256,
+    pub seller_fee: U256,
+    pub royalty_amount: U256,
+}
+
+impl FeeBreakdown {
+    pub(crate) fn new(
+        price: U256,
+        buyer_fee_bps: U256,
+        seller_fee_bps: U256,
+        royalty_bps: U256,
+    ) -> Self {
+        FeeBreakdown {
+            price,
+            buyer_fee: apply_bps(price, buyer_fee_bps),
+            seller_fee: apply_bps(price, seller_fee_bps),
+            royalty_amount: apply_bps(price, royalty_bps),
+        }
+    }
+
+    /// Total amount the buyer pays.
+    pub(crate) fn buyer_total(&self) -> U256 {
+        self.price + self.buyer_fee
+    }
+
+    /// Fee kept by the market, from both sides of the trade.
+    pub(crate) fn market_fee(&self) -> U256 {
+        self.buyer_fee + self.seller_fee
+    }
+
+    /// Amount left for the seller.
+    pub(crate) fn seller_amount(&self) -> U256 {
+        self.price - self.seller_fee - self.royalty_amount
+    }
+}
+
+/// Returns `bps` basis points of `amount`.
+pub(crate) fn apply_bps(amount: U256, bps: U256) -> U256 {
+    amount * bps / U256::from(FEE_DIVISOR)
+}
+
+/// Lowers a fee rate by a discount, both in basis points.
+pub(crate) fn discounted_bps(bps: U256, discount_bps: U256) -> U256 {
+    bps - apply_bps(bps, discount_bps)
+}
+
+/// What a sale at a given price costs the buyer and pays everyone else, as returned by
+/// `get_sale_quote`.
+pub(crate) struct SaleQuote {
+    pub buyer_total: U256,
+    pub market_fee: U256,
+    pub royalty_recipient: Option<Key>,
+    pub royalty_amount: U256,
+    pub seller_amount: U256,
+}
+
+impl SaleQuote {
+    pub(crate) fn new(fees: &FeeBreakdown, royalty_recipient: Option<Key>) -> Self {
+        SaleQuote {
+            buyer_total: fees.buyer_total(),
+            market_fee: fees.market_fee(),
+            royalty_recipient,
+            royalty_amount: fees.royalty_amount,
+            seller_amount: fees.seller_amount(),
+        }
+    }
+}
+
+// Encoded as the tuple ((buyer_total, market_fee), (royalty_recipient, royalty_amount),
+// seller_amount), tuples hold at most three elements so the pairs are nested
+impl CLTyped for SaleQuote {
+    fn cl_type() -> CLType {
+        <((U256, U256), (Option<Key>, U256), U256)>::cl_type()
+    }
+}
+
+impl ToBytes for SaleQuote {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.buyer_total.to_bytes()?);
+        result.append(&mut self.market_fee.to_bytes()?);
+        result.append(&mut self.royalty_recipient.to_bytes()?);
+        result.append(&mut self.royalty_amount.to_bytes()?);
+        result.append(&mut self.seller_amount.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.buyer_total.serialized_length()
+            + self.market_fee.serialized_length()
+            + self.royalty_recipient.serialized_length()
+            + self.royalty_amount.serialized_length()
+            + self.seller_amount.serialized_length()
+    }
+}
+
+impl FromBytes for SaleQuote {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (buyer_total, remainder) = U256::from_bytes(bytes)?;
+        let (market_fee, remainder) = U256::from_bytes(remainder)?;
+        let (royalty_recipient, remainder) = Option::<Key>::from_bytes(remainder)?;
+        let (royalty_amount, remainder) = U256::from_bytes(remainder)?;
+        let (seller_amount, remainder) = U256::from_bytes(remainder)?;
+        Ok((
+            SaleQuote {
+                buyer_total,
+                market_fee,
+                royalty_recipient,
+                royalty_amount,
+                seller_amount,
+            },
+            remainder,
+        ))
+    }
+}
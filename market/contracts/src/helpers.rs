@@ -300,6 +300,11 @@ pub(crate) fn get_named_arg_size(name: &str) -> Option<usize> {
 }
 
 
+/// Reads a named argument that callers may leave out, returning `None` when it is absent.
+pub(crate) fn get_optional_named_arg<T: CLTyped + FromBytes>(name: &str) -> Option<T> {
+    get_named_arg_size(name).map(|_| runtime::get_named_arg(name))
+}
+
 pub(crate) fn get_identifier_mode_from_runtime_args() -> NFTIdentifierMode {
     let identifier_mode_u8: u8 = runtime::get_named_arg(ARG_IDENTIFIER_MODE);
     let identifier_mode =
@@ -342,6 +347,7 @@ pub(crate) fn get_named_arg_with_user_errors<T: FromBytes>(
 pub(crate) enum TokenIdentifier {
      Index(u64),
      Hash(String),
+     TokenId(U256), // CEP-47
 }
 
 impl TokenIdentifier {
@@ -353,6 +359,10 @@ impl TokenIdentifier {
         TokenIdentifier::Hash(hash)
     }
 
+    pub(crate) fn new_token_id(token_id: U256) -> Self {
+        TokenIdentifier::TokenId(token_id)
+    }
+
     pub(crate) fn get_index(&self) -> Option<u64> {
         if let Self::Index(index) = self {
             return Some(*index);
@@ -367,10 +377,18 @@ impl TokenIdentifier {
         None
     }
 
+    pub(crate) fn get_token_id(&self) -> Option<U256> {
+        if let Self::TokenId(token_id) = self {
+            return Some(*token_id);
+        }
+        None
+    }
+
     pub(crate) fn to_string(&self) -> String {
         match self {
             TokenIdentifier::Index(index) => index.to_string(),
-            TokenIdentifier::Hash(hash) => hash.clone()
+            TokenIdentifier::Hash(hash) => hash.clone(),
+            TokenIdentifier::TokenId(token_id) => token_id.to_string()
         }
     }
     pub(crate) fn from_string(value_string: String, identifier_mode: &NFTIdentifierMode) -> Self {
@@ -411,6 +429,16 @@ pub(crate) fn write_dictionary_value_from_key<T: CLTyped + FromBytes + ToBytes>(
     }
 }
 
+// Overwrites whatever is stored under `key`, even a value of another type
+pub(crate) fn put_dictionary_value_from_key<T: CLTyped + ToBytes>(
+    dictionary_name: &str,
+    key: &str,
+    value: T,
+) {
+    let seed_uref = get_uref(dictionary_name);
+    storage::dictionary_put(seed_uref, key, value);
+}
+
 pub(crate) fn get_unlock_id_key(unlock_id: &str) -> String {
     let unlock_id_bytes = unlock_id.as_bytes();
     let key_bytes = runtime::blake2b(unlock_id_bytes);
@@ -426,6 +454,24 @@ pub(crate) fn get_token_market_key(nft_contract_hash: &Key, token_identifier: &T
     hex::encode(&key_bytes)
 }
 
+pub(crate) fn get_claimable_balance_key(account: &Key, currency: &Key) -> String {
+    let account_binding = account.to_string();
+    let account_bytes = account_binding.as_bytes();
+    let currency_binding = currency.to_string();
+    let currency_bytes = currency_binding.as_bytes();
+    let key_bytes = runtime::blake2b([account_bytes, currency_bytes].concat());
+    hex::encode(&key_bytes)
+}
+
+pub(crate) fn get_approved_seller_key(nft_contract_hash: &Key, seller: &Key) -> String {
+    let nft_contract_hash_binding = nft_contract_hash.to_string();
+    let nft_contract_hash_bytes = nft_contract_hash_binding.as_bytes();
+    let seller_binding = seller.to_string();
+    let seller_bytes = seller_binding.as_bytes();
+    let key_bytes = runtime::blake2b([nft_contract_hash_bytes, seller_bytes].concat());
+    hex::encode(&key_bytes)
+}
+
 pub fn u256_to_u512(nb: U256) -> U512 {
     let mut b = [0u8; 32];
     nb.to_big_endian(&mut b);
@@ -0,0 +1,137 @@
+//! Paginated on-chain indexes of token markets.
+//!
+//! Every index is a set of `(nft_contract_hash, token_id)` entries per scope, e.g. the listings of
+//! one collection or the bids of one bidder. A set lives in three dictionaries shared by all
+//! indexes: its size, the entry at each position and the position plus one of each entry, zero
+//! meaning absent. Removing an entry moves the last one into its place, so positions stay dense
+//! and pages are cheap to read.
+use alloc::{string::String, vec::Vec};
+use casper_contract::contract_api::{runtime, storage};
+use casper_contract::unwrap_or_revert::UnwrapOrRevert;
+use casper_types::Key;
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{
+    get_dictionary_value_from_key, write_dictionary_value_from_key, TokenIdentifier,
+};
+
+/// Entry of an index, the collection and the token id as a string.
+pub(crate) type IndexEntry = (Key, String);
+
+/// Creates the dictionaries backing the indexes, if they don't exist yet.
+pub(crate) fn create_index_dictionaries() {
+    for dictionary_name in [INDEX_SIZES, INDEX_ENTRIES, INDEX_POSITIONS].iter() {
+        if runtime::get_key(dictionary_name).is_none() {
+            storage::new_dictionary(dictionary_name)
+                .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+        }
+    }
+}
+
+pub(crate) fn add_to_index(
+    index: &str,
+    scope: &Key,
+    nft_contract_hash: &Key,
+    token_identifier: &TokenIdentifier,
+) {
+    let token_id = token_identifier.to_string();
+    let position_key = get_position_key(index, scope, nft_contract_hash, &token_id);
+    if get_position(&position_key).is_some() {
+        return;
+    }
+
+    let size = get_index_size(index, scope);
+    write_dictionary_value_from_key(
+        INDEX_ENTRIES,
+        &get_entry_key(index, scope, size),
+        (*nft_contract_hash, token_id),
+    );
+    write_dictionary_value_from_key(INDEX_POSITIONS, &position_key, size + 1);
+    write_dictionary_value_from_key(INDEX_SIZES, &get_size_key(index, scope), size + 1);
+}
+
+pub(crate) fn remove_from_index(
+    index: &str,
+    scope: &Key,
+    nft_contract_hash: &Key,
+    token_identifier: &TokenIdentifier,
+) {
+    let token_id = token_identifier.to_string();
+    let position_key = get_position_key(index, scope, nft_contract_hash, &token_id);
+    let position: u64 = match get_position(&position_key) {
+        Some(position) => position,
+        None => return,
+    };
+
+    // Fill the hole with the last entry
+    let last_position = get_index_size(index, scope) - 1;
+    if position != last_position {
+        let last_entry: IndexEntry =
+            get_dictionary_value_from_key(INDEX_ENTRIES, &get_entry_key(index, scope, last_position))
+                .unwrap_or_revert();
+        write_dictionary_value_from_key(
+            INDEX_POSITIONS,
+            &get_position_key(index, scope, &last_entry.0, &last_entry.1),
+            position + 1,
+        );
+        write_dictionary_value_from_key(
+            INDEX_ENTRIES,
+            &get_entry_key(index, scope, position),
+            last_entry,
+        );
+    }
+
+    write_dictionary_value_from_key(INDEX_POSITIONS, &position_key, 0u64);
+    write_dictionary_value_from_key(INDEX_SIZES, &get_size_key(index, scope), last_position);
+}
+
+/// Returns at most `limit` entries of an index, starting at `offset`.
+pub(crate) fn get_index_page(index: &str, scope: &Key, offset: u64, limit: u64) -> Vec<IndexEntry> {
+    let size = get_index_size(index, scope);
+    let end = core::cmp::min(size, offset.saturating_add(limit));
+    let mut page: Vec<IndexEntry> = Vec::new();
+    for position in offset..end {
+        let entry: IndexEntry =
+            get_dictionary_value_from_key(INDEX_ENTRIES, &get_entry_key(index, scope, position))
+                .unwrap_or_revert();
+        page.push(entry);
+    }
+    page
+}
+
+pub(crate) fn get_index_size(index: &str, scope: &Key) -> u64 {
+    get_dictionary_value_from_key(INDEX_SIZES, &get_size_key(index, scope)).unwrap_or_default()
+}
+
+fn get_position(position_key: &str) -> Option<u64> {
+    match get_dictionary_value_from_key::<u64>(INDEX_POSITIONS, position_key) {
+        Some(stored) if stored > 0 => Some(stored - 1),
+        _ => None,
+    }
+}
+
+fn get_size_key(index: &str, scope: &Key) -> String {
+    hash_parts(&[index.as_bytes(), scope.to_formatted_string().as_bytes()])
+}
+
+fn get_entry_key(index: &str, scope: &Key, position: u64) -> String {
+    hash_parts(&[
+        index.as_bytes(),
+        scope.to_formatted_string().as_bytes(),
+        &position.to_le_bytes(),
+    ])
+}
+
+fn get_position_key(index: &str, scope: &Key, nft_contract_hash: &Key, token_id: &str) -> String {
+    hash_parts(&[
+        index.as_bytes(),
+        scope.to_formatted_string().as_bytes(),
+        nft_contract_hash.to_formatted_string().as_bytes(),
+        token_id.as_bytes(),
+    ])
+}
+
+fn hash_parts(parts: &[&[u8]]) -> String {
+    hex::encode(runtime::blake2b(parts.concat()))
+}
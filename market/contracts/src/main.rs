@@ -8,21 +8,32 @@ compile_error!("target arch should be wasm32: compile with '--target wasm32-unkn
 extern crate alloc;
 
 mod address;
+mod collections;
 pub mod constants;
 mod entry_points;
 mod error;
 mod events;
+mod fees;
 mod helpers;
+mod indexes;
 pub mod named_keys;
+mod nft;
+mod pause;
+mod stats;
 use serde::{Deserialize, Serialize};
 
 use crate::constants::*;
 use crate::error::Error;
 use crate::helpers::*;
 use address::Address;
+use collections::{get_collection, get_enabled_collection, Collection};
+use core::convert::TryFrom;
+use nft::NFTStandard;
 
 use alloc::{
+    collections::BTreeSet,
     string::{String, ToString},
+    vec,
     vec::*,
 };
 use casper_contract::{
@@ -33,12 +44,18 @@ use casper_contract::{
     unwrap_or_revert::UnwrapOrRevert,
 };
 use casper_types::{
-    contracts::NamedKeys, runtime_args, CLType, ContractHash, ContractPackageHash, HashAddr, Key,
+    bytesrepr::{self, FromBytes, ToBytes},
+    contracts::NamedKeys,
+    runtime_args, CLType, CLTyped, CLValue, ContractHash, ContractPackageHash, HashAddr, Key,
     RuntimeArgs, URef, U256,
 };
-use events::MarketPlaceEvent;
+use fees::{FeeBreakdown, SaleQuote, CURRENT_FEE_VERSION, FEE_DIVISOR, MAX_MARKET_FEE, MAX_ROYALTY_FEE};
 use helpers::{get_immediate_caller_key, get_self_key, get_token_market_key};
-const FEE_DIVISOR: u64 = 10000;
+use indexes::{add_to_index, get_index_page, remove_from_index, IndexEntry};
+
+// Layout of the contract's named keys and dictionaries, bumped by every release that changes it
+const CURRENT_STORAGE_VERSION: u8 = 2;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct TokenMarket {
     offeror: Option<Key>, //token seller
@@ -46,6 +63,162 @@ pub(crate) struct TokenMarket {
     bidder: Option<Key>,
     locked_bid: U256,
     is_active: Option<bool>,
+    #[serde(default)]
+    locked_fee: U256, // buyer fee escrowed along with locked_bid
+    #[serde(default)]
+    fee_version: u8,
+    #[serde(default)]
+    referrer: Option<Key>, // referrer of the escrowed bid, paid when it settles
+}
+
+impl CLTyped for TokenMarket {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for TokenMarket {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.offeror.to_bytes()?);
+        result.append(&mut self.minimum_offer.to_bytes()?);
+        result.append(&mut self.bidder.to_bytes()?);
+        result.append(&mut self.locked_bid.to_bytes()?);
+        result.append(&mut self.is_active.to_bytes()?);
+        result.append(&mut self.locked_fee.to_bytes()?);
+        result.append(&mut self.fee_version.to_bytes()?);
+        result.append(&mut self.referrer.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.offeror.serialized_length()
+            + self.minimum_offer.serialized_length()
+            + self.bidder.serialized_length()
+            + self.locked_bid.serialized_length()
+            + self.is_active.serialized_length()
+            + self.locked_fee.serialized_length()
+            + self.fee_version.serialized_length()
+            + self.referrer.serialized_length()
+    }
+}
+
+impl FromBytes for TokenMarket {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (offeror, remainder) = Option::<Key>::from_bytes(bytes)?;
+        let (minimum_offer, remainder) = U256::from_bytes(remainder)?;
+        let (bidder, remainder) = Option::<Key>::from_bytes(remainder)?;
+        let (locked_bid, remainder) = U256::from_bytes(remainder)?;
+        let (is_active, remainder) = Option::<bool>::from_bytes(remainder)?;
+        let (locked_fee, remainder) = U256::from_bytes(remainder)?;
+        let (fee_version, remainder) = u8::from_bytes(remainder)?;
+        let (referrer, remainder) = Option::<Key>::from_bytes(remainder)?;
+        Ok((
+            TokenMarket {
+                offeror,
+                minimum_offer,
+                bidder,
+                locked_bid,
+                is_active,
+                locked_fee,
+                fee_version,
+                referrer,
+            },
+            remainder,
+        ))
+    }
+}
+
+// Listing of a token market, as returned by get_listing
+pub(crate) struct TokenListing {
+    seller: Key,
+    minimum_offer: U256,
+    is_active: bool,
+}
+
+// Encoded as the tuple (seller, minimum_offer, is_active), so clients can decode it
+impl CLTyped for TokenListing {
+    fn cl_type() -> CLType {
+        <(Key, U256, bool)>::cl_type()
+    }
+}
+
+impl ToBytes for TokenListing {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.seller.to_bytes()?);
+        result.append(&mut self.minimum_offer.to_bytes()?);
+        result.append(&mut self.is_active.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.seller.serialized_length()
+            + self.minimum_offer.serialized_length()
+            + self.is_active.serialized_length()
+    }
+}
+
+impl FromBytes for TokenListing {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (seller, remainder) = Key::from_bytes(bytes)?;
+        let (minimum_offer, remainder) = U256::from_bytes(remainder)?;
+        let (is_active, remainder) = bool::from_bytes(remainder)?;
+        Ok((
+            TokenListing {
+                seller,
+                minimum_offer,
+                is_active,
+            },
+            remainder,
+        ))
+    }
+}
+
+// Current bid on a token market, as returned by get_bid
+pub(crate) struct TokenBid {
+    bidder: Key,
+    locked_bid: U256,
+    locked_fee: U256,
+}
+
+// Encoded as the tuple (bidder, locked_bid, locked_fee), so clients can decode it
+impl CLTyped for TokenBid {
+    fn cl_type() -> CLType {
+        <(Key, U256, U256)>::cl_type()
+    }
+}
+
+impl ToBytes for TokenBid {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.bidder.to_bytes()?);
+        result.append(&mut self.locked_bid.to_bytes()?);
+        result.append(&mut self.locked_fee.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.bidder.serialized_length()
+            + self.locked_bid.serialized_length()
+            + self.locked_fee.serialized_length()
+    }
+}
+
+impl FromBytes for TokenBid {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (bidder, remainder) = Key::from_bytes(bytes)?;
+        let (locked_bid, remainder) = U256::from_bytes(remainder)?;
+        let (locked_fee, remainder) = U256::from_bytes(remainder)?;
+        Ok((
+            TokenBid {
+                bidder,
+                locked_bid,
+                locked_fee,
+            },
+            remainder,
+        ))
+    }
 }
 
 #[no_mangle]
@@ -59,7 +232,9 @@ pub extern "C" fn init() {
 
     let contract_fee_receiver: Key = runtime::get_named_arg(ARG_MARKET_FEE_RECEIVER);
 
-    let market_fee: U256 = runtime::get_named_arg(ARG_MARKET_FEE);
+    let buyer_fee: U256 = runtime::get_named_arg(ARG_BUYER_FEE);
+
+    let seller_fee: U256 = runtime::get_named_arg(ARG_SELLER_FEE);
 
     let royalty_fee: U256 = runtime::get_named_arg(ARG_ROYALTY_FEE);
 
@@ -67,6 +242,13 @@ pub extern "C" fn init() {
 
     let wcspr_contract: Key = runtime::get_named_arg(ARG_WCSPR_CONTRACT);
 
+    if buyer_fee > U256::from(MAX_MARKET_FEE) || seller_fee > U256::from(MAX_MARKET_FEE) {
+        runtime::revert(Error::FeeTooHigh);
+    }
+    if royalty_fee > U256::from(MAX_ROYALTY_FEE) {
+        runtime::revert(Error::FeeTooHigh);
+    }
+
     runtime::put_key(
         CONTRACT_HASH_KEY_NAME,
         storage::new_uref(contract_hash).into(),
@@ -84,12 +266,22 @@ pub extern "C" fn init() {
 
     runtime::put_key(WCSPR_CONTRACT, storage::new_uref(wcspr_contract).into());
 
-    runtime::put_key(MARKET_FEE, storage::new_uref(market_fee as U256).into());
+    runtime::put_key(BUYER_FEE, storage::new_uref(buyer_fee as U256).into());
+    runtime::put_key(SELLER_FEE, storage::new_uref(seller_fee as U256).into());
     runtime::put_key(ROYALTY_FEE, storage::new_uref(royalty_fee as U256).into());
     runtime::put_key(IS_ROYALTY, storage::new_uref(is_royalty as bool).into());
-    storage::new_dictionary(TOKEN_CONTRACT_MAP)
-        .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    runtime::put_key(FEE_VERSION, storage::new_uref(CURRENT_FEE_VERSION).into());
+    runtime::put_key(REFERRAL_SHARE, storage::new_uref(U256::zero()).into());
+    runtime::put_key(STORAGE_VERSION, storage::new_uref(CURRENT_STORAGE_VERSION).into());
+    events::init_events();
+    storage::new_dictionary(COLLECTIONS).unwrap_or_revert_with(Error::FailedToCreateDictionary);
     storage::new_dictionary(TOKEN_MARKET).unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    storage::new_dictionary(CLAIMABLE_BALANCES)
+        .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    indexes::create_index_dictionaries();
+    stats::create_stats_dictionaries();
+    collections::create_seller_allowlist_dictionaries();
+    pause::create_pause_storage();
 }
 
 #[no_mangle]
@@ -97,20 +289,32 @@ fn call() {
     let contract_name: String = runtime::get_named_arg(MARKET_CONTRACT_NAME);
     let contract_hash_key_name = String::from(contract_name.clone());
     let contract_package_hash_key_name = String::from(contract_name.clone() + "_package_name");
+    let access_uref_key_name = String::from(contract_name.clone() + "_access_uref");
+    let migrate_uref_key_name = String::from(contract_name.clone() + "_migrate_uref");
+
+    // Running the session again from the installing account upgrades the package
+    if let Some(contract_package_hash_key) = runtime::get_key(&contract_package_hash_key_name) {
+        upgrade(contract_hash_key_name, contract_package_hash_key, migrate_uref_key_name);
+        return;
+    }
+
     let contract_owner: Key = runtime::get_named_arg(ARG_CONTRACT_OWNER);
     let market_fee_receiver: Key = runtime::get_named_arg(ARG_MARKET_FEE_RECEIVER);
     let wcspr_contract_hash: Key = runtime::get_named_arg(ARG_WCSPR_CONTRACT);
-    let market_fee: U256 = runtime::get_named_arg(ARG_MARKET_FEE);
+    let buyer_fee: U256 = runtime::get_named_arg(ARG_BUYER_FEE);
+    let seller_fee: U256 = runtime::get_named_arg(ARG_SELLER_FEE);
 
     let royalty_fee: U256 = runtime::get_named_arg(ARG_ROYALTY_FEE);
     let is_royalty: bool = runtime::get_named_arg(ARG_IS_ROYALTY);
 
     let (contract_package_hash, access_uref) = storage::create_contract_package_at_hash();
+    runtime::put_key(&contract_package_hash_key_name, contract_package_hash.into());
+    runtime::put_key(&access_uref_key_name, access_uref.into());
+    create_migrate_group(contract_package_hash, &migrate_uref_key_name);
 
     let named_keys: NamedKeys = named_keys::default(
         contract_owner,
         market_fee_receiver,
-        market_fee,
         contract_package_hash,
         royalty_fee,
         None,
@@ -132,7 +336,8 @@ fn call() {
             ARG_CONTRACT_HASH => Key::from(contract_hash),
             ARG_CONTRACT_OWNER => Key::from(contract_owner),
             ARG_MARKET_FEE_RECEIVER => Key::from(market_fee_receiver),
-            ARG_MARKET_FEE => market_fee,
+            ARG_BUYER_FEE => buyer_fee,
+            ARG_SELLER_FEE => seller_fee,
             ARG_WCSPR_CONTRACT => Key::from(wcspr_contract_hash),
             ARG_ROYALTY_FEE => royalty_fee,
             ARG_IS_ROYALTY => is_royalty,
@@ -140,27 +345,133 @@ fn call() {
     );
 }
 
+// Adds a contract version with the current entry points, the named keys of the previous version
+// carry over. The previous version is disabled and the storage of the new one is migrated.
+fn upgrade(
+    contract_hash_key_name: String,
+    contract_package_hash_key: Key,
+    migrate_uref_key_name: String,
+) {
+    let contract_package_hash_addr: HashAddr =
+        contract_package_hash_key.into_hash().unwrap_or_revert();
+    let contract_package_hash = ContractPackageHash::new(contract_package_hash_addr);
+    let previous_contract_hash: Option<Key> = runtime::get_key(&contract_hash_key_name);
+    // Packages installed before the migrate group get it on their first upgrade
+    if runtime::get_key(&migrate_uref_key_name).is_none() {
+        create_migrate_group(contract_package_hash, &migrate_uref_key_name);
+    }
+
+    let (contract_hash, _version) = storage::add_contract_version(
+        contract_package_hash: ContractPackageHash,
+        entry_points::default(),
+        NamedKeys::new(),
+    );
+    if let Some(previous_contract_hash) = previous_contract_hash {
+        let previous_contract_hash_addr: HashAddr =
+            previous_contract_hash.into_hash().unwrap_or_revert();
+        storage::disable_contract_version(
+            contract_package_hash,
+            ContractHash::new(previous_contract_hash_addr),
+        )
+        .unwrap_or_revert();
+    }
+    runtime::put_key(contract_hash_key_name.as_str(), Key::from(contract_hash));
+
+    runtime::call_contract::<()>(
+        contract_hash,
+        MIGRATE_ENTRY_POINT_NAME,
+        runtime_args! {
+            ARG_CONTRACT_HASH => Key::from(contract_hash),
+        },
+    );
+}
+
+// Only holders of a URef of the migrate group can call migrate, the installing account gets one
+fn create_migrate_group(contract_package_hash: ContractPackageHash, migrate_uref_key_name: &str) {
+    let migrate_urefs: Vec<URef> = storage::create_contract_user_group(
+        contract_package_hash,
+        MIGRATE_GROUP,
+        1,
+        BTreeSet::new(),
+    )
+    .unwrap_or_revert();
+    runtime::put_key(migrate_uref_key_name, migrate_urefs[0].into());
+}
+
+// Brings the storage of an upgraded contract to CURRENT_STORAGE_VERSION, every step runs once.
+// Contracts installed before storage versions are at version 0. The upgrade session calls it on
+// the new contract version with the hash add_contract_version returned for it.
+#[no_mangle]
+pub extern "C" fn migrate() -> Result<(), Error> {
+    let storage_version: u8 = get_key(STORAGE_VERSION).unwrap_or_default();
+    if storage_version < 1 {
+        // Collections registry, indexes, statistics, seller allowlists and pauses
+        create_missing_storage();
+    }
+    if storage_version < 2 {
+        // Registers the CES schemas, or re-registers them with the seller allowlist and pause
+        // events that came after they were first registered
+        events::init_events();
+    }
+    set_key(STORAGE_VERSION, CURRENT_STORAGE_VERSION);
+
+    let contract_hash: Key = runtime::get_named_arg(ARG_CONTRACT_HASH);
+    set_key(CONTRACT_HASH_KEY_NAME, contract_hash);
+    Ok(())
+}
+
+// Named keys and dictionaries added after the first release, for contracts installed before them
+fn create_missing_storage() {
+    if runtime::get_key(COLLECTIONS).is_none() {
+        storage::new_dictionary(COLLECTIONS).unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    }
+    collections::register_legacy_collections();
+    indexes::create_index_dictionaries();
+    stats::create_stats_dictionaries();
+    collections::create_seller_allowlist_dictionaries();
+    pause::create_pause_storage();
+}
+
 fn get_token_market(contract_hash: &Key, token_identifier: &TokenIdentifier) -> TokenMarket {
+    read_token_market(contract_hash, token_identifier).unwrap_or_revert()
+}
+
+// Like get_token_market, without reverting when the token was never offered
+fn read_token_market(contract_hash: &Key, token_identifier: &TokenIdentifier) -> Option<TokenMarket> {
     let token_market_key = get_token_market_key(contract_hash, token_identifier);
-    let token_market_str =
-        get_dictionary_value_from_key::<String>(TOKEN_MARKET, &token_market_key).unwrap_or_revert();
-    let token_market = casper_serde_json_wasm::from_str::<TokenMarket>(&token_market_str).unwrap();
-    token_market
+    get_token_market_by_key(&token_market_key)
+}
+
+// Token markets written before the bytesrepr encoding are still JSON strings until migrated,
+// reading them as bytesrepr fails on the type so they fall back to the JSON read
+fn get_token_market_by_key(token_market_key: &str) -> Option<TokenMarket> {
+    get_dictionary_value_from_key::<TokenMarket>(TOKEN_MARKET, token_market_key)
+        .or_else(|| get_legacy_token_market(token_market_key))
+}
+
+fn get_legacy_token_market(token_market_key: &str) -> Option<TokenMarket> {
+    get_dictionary_value_from_key::<String>(TOKEN_MARKET, token_market_key).and_then(
+        |token_market_str| casper_serde_json_wasm::from_str::<TokenMarket>(&token_market_str).ok(),
+    )
+}
+
+fn write_token_market(token_market_key: &str, token_market: TokenMarket) {
+    put_dictionary_value_from_key(TOKEN_MARKET, token_market_key, token_market);
 }
 
 #[no_mangle]
 pub extern "C" fn offer() {
     let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
     // Check if nft is supported or not
-    check_enabled_nft(contract_hash: Key);
+    let collection = get_enabled_collection(&contract_hash);
+    pause::require_listing_open(&contract_hash);
     // Take token_id from runtime
-    let identifier_mode = get_identifier_mode_from_runtime_args();
-    let token_identifier = get_token_identifier_from_runtime_args(&identifier_mode);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
 
     let minimum_offer: U256 = runtime::get_named_arg(ARG_MINIMUM_OFFER);
 
     let caller = get_immediate_caller_key();
-    let owner_of = get_token_owner(&contract_hash, &identifier_mode, &token_identifier);
+    let owner_of = nft::get_token_owner(&contract_hash, &collection, &token_identifier);
     if caller != owner_of {
         runtime::revert(Error::OnlyOwnerCanOffer);
     }
@@ -168,6 +479,9 @@ pub extern "C" fn offer() {
     if minimum_offer == U256::zero() {
         runtime::revert(Error::AskForMore);
     }
+    if !collections::can_list(&contract_hash, &caller) {
+        runtime::revert(Error::SellerNotApproved);
+    }
 
     // Check if NFT IS APPROVED or revert
     // let contract_hash_addr: HashAddr = contract_hash.into_hash().unwrap_or_revert();
@@ -215,7 +529,6 @@ pub extern "C" fn offer() {
 
     set_offer(
         &contract_hash,
-        &identifier_mode,
         &token_identifier,
         Some(caller),
         minimum_offer,
@@ -226,34 +539,35 @@ pub extern "C" fn offer() {
 pub extern "C" fn bid() {
     let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
     // Check if nft is supported or not
-    check_enabled_nft(contract_hash: Key);
+    let collection = get_enabled_collection(&contract_hash);
+    pause::require_bidding_open(&contract_hash);
     // Check if NFT IS APPROVED or revert
     // let contract_hash_addr: HashAddr = contract_hash.into_hash().unwrap_or_revert();
     // let contract_hash_1: ContractHash = ContractHash::new(contract_hash_addr);
-    let identifier_mode = get_identifier_mode_from_runtime_args();
-    let token_identifier = get_token_identifier_from_runtime_args(&identifier_mode);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
 
     let bidding_offer: U256 = runtime::get_named_arg(ARG_BIDDING_OFFER);
+    let referrer: Option<Key> = get_optional_named_arg(ARG_REFERRER);
 
     let caller = get_immediate_caller_key();
     let caller_addr: Address =
         get_immediate_caller_address().unwrap_or_revert_with(Error::MissingKey);
 
     let token_market_key: String = get_token_market_key(&contract_hash, &token_identifier.clone()); // Key for TOKEN_MARKET dictionary
-    let token_market_str =
-        get_dictionary_value_from_key::<String>(TOKEN_MARKET, &token_market_key).unwrap_or_revert();
-
-    let token_market = casper_serde_json_wasm::from_str::<TokenMarket>(&token_market_str);
+    let token_market = get_token_market_by_key(&token_market_key);
     // let mut existing_bidder = None;
     let mut locked_bid = U256::zero();
 
-    if token_market.is_ok() {
+    if token_market.is_some() {
         let unwrap = token_market.unwrap();
         // existing_bidder = unwrap.bidder;
         locked_bid = unwrap.locked_bid;
         let minimum: U256 = unwrap.minimum_offer;
         // }
 
+        if !unwrap.is_active.unwrap_or(false) {
+            runtime::revert(Error::OfferInactive)
+        }
         if bidding_offer < locked_bid {
             runtime::revert(Error::BidTooLow)
         };
@@ -261,15 +575,6 @@ pub extern "C" fn bid() {
             runtime::revert(Error::InvalidAccount)
         }
 
-        //Calculate needed_amount
-        let fee_portion: U256 = helpers::get_stored_value_with_user_errors(
-            MARKET_FEE,
-            Error::MissingFeePortion,
-            Error::InvalidFeePortion,
-        );
-        let needed_amount: U256 =
-            unwrap.minimum_offer + unwrap.minimum_offer * fee_portion / U256::from("1000");
-
         let wcspr_contract_hash: Key = helpers::get_stored_value_with_user_errors(
             WCSPR_CONTRACT,
             Error::MissingWcsprContract,
@@ -282,17 +587,39 @@ pub extern "C" fn bid() {
         // let contract_self_address = get_self_address().unwrap_or_revert_with(Error::MissingKey);
 
         if bidding_offer >= unwrap.minimum_offer {
+            // The bid being replaced gets its escrow back
+            if let Some(previous_bidder) = unwrap.bidder {
+                credit_claimable_balance(
+                    previous_bidder,
+                    wcspr_contract_hash,
+                    unwrap.locked_bid + unwrap.locked_fee,
+                );
+                remove_from_index(BIDS_BY_BIDDER, &previous_bidder, &contract_hash, &token_identifier);
+                events::emit(events::BidRefunded {
+                    collection: contract_hash,
+                    token_id: token_identifier.to_string(),
+                    bidder: previous_bidder,
+                    currency: wcspr_contract_hash,
+                    amount: unwrap.locked_bid + unwrap.locked_fee,
+                });
+            }
             do_trade(
                 token_market_key: String,
                 &contract_hash: &Key,
                 &token_identifier: &TokenIdentifier,
-                &identifier_mode: &NFTIdentifierMode,
+                &collection: &Collection,
                 unwrap.offeror.unwrap(): Key,
                 caller: Key,
                 unwrap.minimum_offer: U256,
+                U256::zero(), // nothing escrowed yet for the caller
                 wcspr_contract_hash: Key, // ERC20 token contract
+                referrer: Option<Key>,
             );
         } else {
+            if let Some(previous_bidder) = unwrap.bidder {
+                remove_from_index(BIDS_BY_BIDDER, &previous_bidder, &contract_hash, &token_identifier);
+            }
+            add_to_index(BIDS_BY_BIDDER, &caller, &contract_hash, &token_identifier);
             update_new_bidder(
                 token_market_key: String,
                 bidding_offer: U256,
@@ -300,6 +627,10 @@ pub extern "C" fn bid() {
                 caller: Key,
                 wcspr_contract_hash: Key, // ERC20 token contract
                 contract_self_key: Key,
+                &contract_hash: &Key,
+                &token_identifier: &TokenIdentifier,
+                &collection: &Collection,
+                referrer: Option<Key>,
                 // caller_addr: Address,
             );
         }
@@ -309,9 +640,9 @@ pub extern "C" fn bid() {
 #[no_mangle]
 pub extern "C" fn revoke_bid() {
     let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
-    check_enabled_nft(contract_hash: Key);
-    let identifier_mode = get_identifier_mode_from_runtime_args();
-    let token_identifier = get_token_identifier_from_runtime_args(&identifier_mode);
+    // Exit path, stays open while the collection is disabled or paused
+    let collection = collections::get_collection_for_exit(&contract_hash);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
     let token_market_key_to_update = get_token_market_key(&contract_hash, &token_identifier);
     let token_market = get_token_market(&contract_hash, &token_identifier);
     let caller = get_immediate_caller_key();
@@ -331,38 +662,45 @@ pub extern "C" fn revoke_bid() {
     let contract_self_key = get_self_key();
 
     let contract_hash_addr: HashAddr = wcspr_contract_hash.into_hash().unwrap_or_revert();
-    let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
+    let wcspr_hash: ContractHash = ContractHash::new(contract_hash_addr);
 
     let _: () = runtime::call_contract(
-        contract_hash,
+        wcspr_hash,
         TRANSFER_ENTRY_POINT_NAME,
         runtime_args! {
             "recipient" => caller,
-            "amount" => token_market.locked_bid,
+            "amount" => token_market.locked_bid + token_market.locked_fee,
         },
     );
 
     // When revoke-bid => token_market will be set to its origin
-    write_dictionary_value_from_key(
-        TOKEN_MARKET,
-        &token_market_key_to_update,
-        casper_serde_json_wasm::to_string_pretty(&TokenMarket {
-            offeror: Some(token_market.offeror.unwrap()),
-            minimum_offer: token_market.minimum_offer,
-            bidder: None,
-            locked_bid: U256::zero(),
-            is_active: Some(true),
-        })
-        .unwrap(),
-    );
+    write_token_market(&token_market_key_to_update, TokenMarket {
+        offeror: Some(token_market.offeror.unwrap()),
+        minimum_offer: token_market.minimum_offer,
+        bidder: None,
+        locked_bid: U256::zero(),
+        is_active: Some(true),
+        locked_fee: U256::zero(),
+        fee_version: CURRENT_FEE_VERSION,
+        referrer: None,
+    });
+    remove_from_index(BIDS_BY_BIDDER, &caller, &contract_hash, &token_identifier);
+    events::emit(events::BidRevoked {
+        collection: contract_hash,
+        token_id: token_identifier.to_string(),
+        bidder: caller,
+        currency: wcspr_contract_hash,
+        amount: token_market.locked_bid + token_market.locked_fee,
+    });
 }
 #[no_mangle]
 pub extern "C" fn increase_bid() {
     let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
-    check_enabled_nft(contract_hash: Key);
+    let collection = get_enabled_collection(&contract_hash);
+    pause::require_bidding_open(&contract_hash);
     let new_offer: U256 = runtime::get_named_arg(ARG_NEW_OFFER);
-    let identifier_mode = get_identifier_mode_from_runtime_args();
-    let token_identifier = get_token_identifier_from_runtime_args(&identifier_mode);
+    let referrer: Option<Key> = get_optional_named_arg(ARG_REFERRER);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
     let token_market_key_to_update = get_token_market_key(&contract_hash, &token_identifier);
     let token_market = get_token_market(&contract_hash, &token_identifier);
     let caller = get_immediate_caller_key();
@@ -376,20 +714,16 @@ pub extern "C" fn increase_bid() {
         runtime::revert(Error::AskForMore)
     }
 
-    // Transfer the increased amount
-    let increased_amount: U256 = new_offer - token_market.locked_bid;
+    // Escrow already held for this bidder
+    let escrowed: U256 = token_market.locked_bid + token_market.locked_fee;
+    // A bid raised without a referrer keeps the one it was placed with
+    let referrer: Option<Key> = referrer.or(token_market.referrer);
+    check_referrer(referrer, caller, token_market.offeror.unwrap());
 
     // let new_src_purse: URef = runtime::get_named_arg(ARG_SRC_PURSE);
     // let contract_purse_key = runtime::get_key(CONTRACT_PURSE).unwrap_or_revert();
     // let contract_purse = *contract_purse_key.as_uref().unwrap_or_revert();
 
-    let fee_portion: U256 = helpers::get_stored_value_with_user_errors(
-        MARKET_FEE,
-        Error::MissingFeePortion,
-        Error::InvalidFeePortion,
-    );
-    let total_increased_amount: U256 =
-        increased_amount + increased_amount * fee_portion / U256::from("1000");
     let wcspr_contract: Key = helpers::get_stored_value_with_user_errors(
         WCSPR_CONTRACT,
         Error::MissingWcsprContract,
@@ -404,44 +738,53 @@ pub extern "C" fn increase_bid() {
             token_market_key_to_update: String,
             &contract_hash: &Key,
             &token_identifier: &TokenIdentifier,
-            &identifier_mode: &NFTIdentifierMode,
+            &collection: &Collection,
             token_market.offeror.unwrap(): Key,
             caller: Key,
             token_market.minimum_offer: U256,
+            escrowed: U256,
             wcspr_contract: Key, // ERC20 token contract
+            referrer: Option<Key>,
         );
     } else {
-        // transfer_from the increased amount from bidder to contract
-        let contract_hash_addr: HashAddr = wcspr_contract.into_hash().unwrap_or_revert();
-        let wcspr_contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
+        // Escrow the new bid with its buyer fee, the bidder only adds the difference
+        let fees = get_sale_fees(caller, token_market.offeror.unwrap(), new_offer, &collection);
 
-        let _: () = runtime::call_contract(
-            wcspr_contract_hash,
-            TRANSFER_FROM_ENTRY_POINT_NAME,
-            runtime_args! {
-                "owner" => caller,
-                "recipient" => contract_self_key,
-                "amount" => increased_amount,
-            },
-        );
+        if fees.buyer_total() > escrowed {
+            // transfer_from the increased amount from bidder to contract
+            let contract_hash_addr: HashAddr = wcspr_contract.into_hash().unwrap_or_revert();
+            let wcspr_contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
 
-        write_dictionary_value_from_key(
-            TOKEN_MARKET,
-            &token_market_key_to_update,
-            casper_serde_json_wasm::to_string_pretty(&TokenMarket {
-                offeror: Some(token_market.offeror.unwrap()),
-                minimum_offer: token_market.minimum_offer,
-                bidder: Some(caller),
-                locked_bid: new_offer,
-                is_active: Some(true),
-            })
-            .unwrap(),
-        );
-        events::emit(&MarketPlaceEvent::Bid {
-            token_market_key: token_market_key_to_update,
-            offeror: token_market.offeror.unwrap(),
+            let _: () = runtime::call_contract(
+                wcspr_contract_hash,
+                TRANSFER_FROM_ENTRY_POINT_NAME,
+                runtime_args! {
+                    "owner" => caller,
+                    "recipient" => contract_self_key,
+                    "amount" => fees.buyer_total() - escrowed,
+                },
+            );
+        } else {
+            // Fee rate went down since the last bid
+            credit_claimable_balance(caller, wcspr_contract, escrowed - fees.buyer_total());
+        }
+
+        write_token_market(&token_market_key_to_update, TokenMarket {
+            offeror: Some(token_market.offeror.unwrap()),
+            minimum_offer: token_market.minimum_offer,
+            bidder: Some(caller),
+            locked_bid: new_offer,
+            is_active: Some(true),
+            locked_fee: fees.buyer_fee,
+            fee_version: CURRENT_FEE_VERSION,
+            referrer: referrer,
+        });
+        events::emit(events::BidRaised {
+            collection: contract_hash,
+            token_id: token_identifier.to_string(),
             bidder: caller,
-            value: new_offer,
+            currency: wcspr_contract,
+            amount: new_offer,
         });
     }
 }
@@ -450,10 +793,10 @@ pub extern "C" fn increase_bid() {
 pub extern "C" fn revoke_offer() {
     let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
 
-    check_enabled_nft(contract_hash: Key);
+    // Exit path, stays open while the collection is disabled or paused
+    let collection = collections::get_collection_for_exit(&contract_hash);
 
-    let identifier_mode = get_identifier_mode_from_runtime_args();
-    let token_identifier = get_token_identifier_from_runtime_args(&identifier_mode);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
     let token_market_key_to_update = get_token_market_key(&contract_hash, &token_identifier);
     let token_market = get_token_market(&contract_hash, &token_identifier);
     let caller = get_immediate_caller_key();
@@ -469,33 +812,45 @@ pub extern "C" fn revoke_offer() {
         Error::MissingWcsprContract,
         Error::InvalidWcsprContract,
     );
-    let contract_hash_addr: HashAddr = wcspr_contract.into_hash().unwrap_or_revert();
-    let wspr_contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
 
+    // Escrow goes back to the bidder's claimable balance
     if token_market.locked_bid > U256::zero() {
-        let _: () = runtime::call_contract(
-            wspr_contract_hash,
-            TRANSFER_ENTRY_POINT_NAME,
-            runtime_args! {
-                "recipient" => caller,
-                "amount" => token_market.locked_bid,
-            },
+        credit_claimable_balance(
+            token_market.bidder.unwrap(),
+            wcspr_contract,
+            token_market.locked_bid + token_market.locked_fee,
         );
+        events::emit(events::BidRefunded {
+            collection: contract_hash,
+            token_id: token_identifier.to_string(),
+            bidder: token_market.bidder.unwrap(),
+            currency: wcspr_contract,
+            amount: token_market.locked_bid + token_market.locked_fee,
+        });
     }
 
     // When revoke-offer => token_market will be set is_active to false
-    write_dictionary_value_from_key(
-        TOKEN_MARKET,
-        &token_market_key_to_update,
-        casper_serde_json_wasm::to_string_pretty(&TokenMarket {
-            offeror: Some(token_market.offeror.unwrap()),
-            minimum_offer: U256::zero(),
-            bidder: None,
-            locked_bid: U256::zero(),
-            is_active: Some(false),
-        })
-        .unwrap(),
+    write_token_market(&token_market_key_to_update, TokenMarket {
+        offeror: Some(token_market.offeror.unwrap()),
+        minimum_offer: U256::zero(),
+        bidder: None,
+        locked_bid: U256::zero(),
+        is_active: Some(false),
+        locked_fee: U256::zero(),
+        fee_version: CURRENT_FEE_VERSION,
+        referrer: None,
+    });
+    remove_from_indexes(
+        &contract_hash,
+        &token_identifier,
+        token_market.offeror.unwrap(),
+        token_market.bidder,
     );
+    events::emit(events::ListingRevoked {
+        collection: contract_hash,
+        token_id: token_identifier.to_string(),
+        seller: token_market.offeror.unwrap(),
+    });
 }
 
 #[no_mangle]
@@ -503,10 +858,10 @@ pub extern "C" fn change_offer() {
     let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
     let new_minimum_offer: U256 = runtime::get_named_arg(ARG_NEW_MINIMUM_OFFER);
 
-    check_enabled_nft(contract_hash: Key);
+    let collection = get_enabled_collection(&contract_hash);
+    pause::require_listing_open(&contract_hash);
 
-    let identifier_mode = get_identifier_mode_from_runtime_args();
-    let token_identifier = get_token_identifier_from_runtime_args(&identifier_mode);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
     let token_market_key_to_update = get_token_market_key(&contract_hash, &token_identifier);
     let token_market = get_token_market(&contract_hash, &token_identifier);
     let caller = get_immediate_caller_key();
@@ -531,48 +886,235 @@ pub extern "C" fn change_offer() {
                 token_market_key_to_update: String,
                 &contract_hash: &Key,
                 &token_identifier: &TokenIdentifier,
-                &identifier_mode: &NFTIdentifierMode,
+                &collection: &Collection,
                 token_market.offeror.unwrap(): Key,
                 token_market.bidder.unwrap(): Key,
                 new_minimum_offer: U256,
+                token_market.locked_bid + token_market.locked_fee,
                 wcspr_contract: Key,
+                token_market.referrer: Option<Key>,
             )
         } else {
-            write_dictionary_value_from_key(
-                TOKEN_MARKET,
-                &token_market_key_to_update,
-                casper_serde_json_wasm::to_string_pretty(&TokenMarket {
-                    offeror: Some(token_market.offeror.unwrap()),
-                    minimum_offer: new_minimum_offer,
-                    bidder: Some(token_market.offeror.unwrap()),
-                    locked_bid: token_market.locked_bid,
-                    is_active: Some(true),
-                })
-                .unwrap(),
-            );
-        }
-    } else {
-        write_dictionary_value_from_key(
-            TOKEN_MARKET,
-            &token_market_key_to_update,
-            casper_serde_json_wasm::to_string_pretty(&TokenMarket {
+            write_token_market(&token_market_key_to_update, TokenMarket {
                 offeror: Some(token_market.offeror.unwrap()),
                 minimum_offer: new_minimum_offer,
-                bidder: None,
-                locked_bid: U256::zero(),
+                bidder: token_market.bidder,
+                locked_bid: token_market.locked_bid,
                 is_active: Some(true),
-            })
-            .unwrap(),
-        );
+                locked_fee: token_market.locked_fee,
+                fee_version: token_market.fee_version,
+                referrer: token_market.referrer,
+            });
+            events::emit(events::ListingChanged {
+                collection: contract_hash,
+                token_id: token_identifier.to_string(),
+                seller: token_market.offeror.unwrap(),
+                minimum_offer: new_minimum_offer,
+            });
+        }
+    } else {
+        write_token_market(&token_market_key_to_update, TokenMarket {
+            offeror: Some(token_market.offeror.unwrap()),
+            minimum_offer: new_minimum_offer,
+            bidder: None,
+            locked_bid: U256::zero(),
+            is_active: Some(true),
+            locked_fee: U256::zero(),
+            fee_version: CURRENT_FEE_VERSION,
+            referrer: None,
+        });
+        events::emit(events::ListingChanged {
+            collection: contract_hash,
+            token_id: token_identifier.to_string(),
+            seller: token_market.offeror.unwrap(),
+            minimum_offer: new_minimum_offer,
+        });
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn withdraw() {
+    let currency: Key = runtime::get_named_arg(ARG_CURRENCY);
+    let caller = get_immediate_caller_key();
+
+    let claimable_balance_key = get_claimable_balance_key(&caller, &currency);
+    let balance: U256 =
+        get_dictionary_value_from_key(CLAIMABLE_BALANCES, &claimable_balance_key).unwrap_or_default();
+    if balance == U256::zero() {
+        runtime::revert(Error::NothingToWithdraw);
     }
+    // Clear the balance before paying out
+    write_dictionary_value_from_key(CLAIMABLE_BALANCES, &claimable_balance_key, U256::zero());
+
+    let contract_hash_addr: HashAddr = currency.into_hash().unwrap_or_revert();
+    let currency_contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
+    let _: () = runtime::call_contract(
+        currency_contract_hash,
+        TRANSFER_ENTRY_POINT_NAME,
+        runtime_args! {
+            "recipient" => caller,
+            "amount" => balance,
+        },
+    );
+
+    events::emit(events::Withdrawal {
+        recipient: caller,
+        currency: currency,
+        amount: balance,
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn get_claimable_balance() {
+    let account: Key = runtime::get_named_arg(ARG_ACCOUNT);
+    let currency: Key = runtime::get_named_arg(ARG_CURRENCY);
+
+    let claimable_balance_key = get_claimable_balance_key(&account, &currency);
+    let balance: U256 =
+        get_dictionary_value_from_key(CLAIMABLE_BALANCES, &claimable_balance_key).unwrap_or_default();
+
+    let return_value =
+        CLValue::from_t(balance).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Listing of a token as (seller, minimum_offer, is_active), None if it was never offered or was
+// sold. Listings have no expiry, a listing stays active until it is sold or revoked.
+#[no_mangle]
+pub extern "C" fn get_listing() {
+    let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let collection = get_collection(&contract_hash);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
+
+    let token_listing: Option<TokenListing> = read_token_market(&contract_hash, &token_identifier)
+        .and_then(|token_market| {
+            token_market.offeror.map(|seller| TokenListing {
+                seller,
+                minimum_offer: token_market.minimum_offer,
+                is_active: token_market.is_active.unwrap_or(false),
+            })
+        });
+
+    let return_value =
+        CLValue::from_t(token_listing).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Current bid on a token as (bidder, locked_bid, locked_fee), None if nobody is bidding
+#[no_mangle]
+pub extern "C" fn get_bid() {
+    let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let collection = get_collection(&contract_hash);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
+
+    let token_bid: Option<TokenBid> = read_token_market(&contract_hash, &token_identifier)
+        .and_then(|token_market| {
+            token_market.bidder.map(|bidder| TokenBid {
+                bidder,
+                locked_bid: token_market.locked_bid,
+                locked_fee: token_market.locked_fee,
+            })
+        });
+
+    let return_value =
+        CLValue::from_t(token_bid).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Active listings of a collection, as (nft_contract_hash, token_id) pairs
+#[no_mangle]
+pub extern "C" fn get_listings_by_collection() {
+    let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    ret_index_page(LISTINGS_BY_COLLECTION, &contract_hash);
+}
+
+// Active listings of a seller, as (nft_contract_hash, token_id) pairs
+#[no_mangle]
+pub extern "C" fn get_listings_by_seller() {
+    let seller: Key = runtime::get_named_arg(ARG_SELLER);
+    ret_index_page(LISTINGS_BY_SELLER, &seller);
+}
+
+// Tokens a bidder currently holds the bid on, as (nft_contract_hash, token_id) pairs
+#[no_mangle]
+pub extern "C" fn get_bids_by_bidder() {
+    let bidder: Key = runtime::get_named_arg(ARG_BIDDER);
+    ret_index_page(BIDS_BY_BIDDER, &bidder);
+}
+
+fn ret_index_page(index: &str, scope: &Key) {
+    let offset: u64 = runtime::get_named_arg(ARG_OFFSET);
+    let limit: u64 = runtime::get_named_arg(ARG_LIMIT);
+
+    let page: Vec<IndexEntry> = get_index_page(index, scope, offset, limit);
+
+    let return_value = CLValue::from_t(page).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Trading statistics of a collection
+#[no_mangle]
+pub extern "C" fn get_collection_stats() {
+    let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+
+    let collection_stats = stats::get_collection_stats(&contract_hash);
+
+    let return_value =
+        CLValue::from_t(collection_stats).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Last price a token sold for on the market, None if it never traded
+#[no_mangle]
+pub extern "C" fn get_token_last_sale() {
+    let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let collection = get_collection(&contract_hash);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
+
+    let token_market_key = get_token_market_key(&contract_hash, &token_identifier);
+    let last_sale: Option<U256> = stats::get_token_last_sale(&token_market_key);
+
+    let return_value =
+        CLValue::from_t(last_sale).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Quote for a buyer, the caller by default, buying a token at `price`, split exactly as do_trade
+// would settle it
+#[no_mangle]
+pub extern "C" fn get_sale_quote() {
+    let contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let collection = get_collection(&contract_hash);
+    let token_identifier = nft::get_token_identifier_from_runtime_args(&collection);
+    let price: U256 = runtime::get_named_arg(ARG_PRICE);
+
+    let buyer: Key = get_optional_named_arg(ARG_BUYER).unwrap_or_else(get_immediate_caller_key);
+
+    // The seller is the offeror of an active listing, otherwise the current owner of the token
+    let offeror: Option<Key> = read_token_market(&contract_hash, &token_identifier)
+        .filter(|token_market| token_market.is_active.unwrap_or(false))
+        .and_then(|token_market| token_market.offeror);
+    let seller: Key = match offeror {
+        Some(offeror) => offeror,
+        None => nft::get_token_owner(&contract_hash, &collection, &token_identifier),
+    };
+
+    let fees = get_sale_fees(buyer, seller, price, &collection);
+    let royalty_recipient: Option<Key> = if fees.royalty_amount > U256::zero() {
+        Some(collection.creator)
+    } else {
+        None
+    };
+
+    let return_value = CLValue::from_t(SaleQuote::new(&fees, royalty_recipient))
+        .unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
 }
 
 #[no_mangle]
 pub extern "C" fn set_support_token() -> Result<(), Error> {
     let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
 
-    let nft_contract_str_key = helpers::make_dictionary_item_key_for_key(nft_contract_hash);
-
     // let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
 
     let caller = get_immediate_caller_key();
@@ -588,6 +1130,7 @@ pub extern "C" fn set_support_token() -> Result<(), Error> {
         runtime::revert(Error::InvalidContractOwner);
     }
     let mut token_list = get_key::<Vec<Key>>(TOKEN_CONTRACT_LIST).unwrap_or_revert();
+    let existing_collection = collections::read_collection(&nft_contract_hash);
 
     let nft_enabled: bool = runtime::get_named_arg(ARG_NFT_ENABLED);
     if nft_enabled {
@@ -596,23 +1139,181 @@ pub extern "C" fn set_support_token() -> Result<(), Error> {
             set_key(TOKEN_CONTRACT_LIST, token_list);
         }
 
-        write_dictionary_value_from_key(TOKEN_CONTRACT_MAP, &nft_contract_str_key, true);
+        // Royalty defaults to the market wide rate
+        let royalty_fee: U256 = get_optional_named_arg(ARG_ROYALTY_FEE).unwrap_or_else(|| {
+            helpers::get_stored_value_with_user_errors(
+                ROYALTY_FEE,
+                Error::MissingRoyaltyFee,
+                Error::InvalidRoyaltyFee,
+            )
+        });
+        if royalty_fee > U256::from(MAX_ROYALTY_FEE) {
+            runtime::revert(Error::FeeTooHigh);
+        }
+        // Re-enabling a collection keeps the date it was first added
+        let added_at: u64 = match existing_collection {
+            Some(collection) => collection.added_at,
+            None => runtime::get_blocktime().into(),
+        };
+
+        // CEP-78 unless told otherwise, CEP-47 has no identifier mode
+        let standard: u8 =
+            get_optional_named_arg(ARG_NFT_STANDARD).unwrap_or(NFTStandard::CEP78 as u8);
+        let identifier_mode: u8 = match NFTStandard::try_from(standard).unwrap_or_revert() {
+            NFTStandard::CEP78 => get_identifier_mode_from_runtime_args() as u8,
+            NFTStandard::CEP47 => NFTIdentifierMode::Ordinal as u8,
+        };
+
+        collections::write_collection(
+            &nft_contract_hash,
+            Collection {
+                name: runtime::get_named_arg(ARG_COLLECTION_NAME),
+                identifier_mode,
+                creator: runtime::get_named_arg(ARG_CREATOR),
+                royalty_fee,
+                added_at,
+                enabled: true,
+                standard,
+            },
+        );
     } else {
         if token_list.contains(&nft_contract_hash) {
             token_list.retain(|x| *x != nft_contract_hash);
             set_key(TOKEN_CONTRACT_LIST, token_list);
         }
-        write_dictionary_value_from_key(TOKEN_CONTRACT_MAP, &nft_contract_str_key, false);
-
-        // write_dictionary_value_from_key(TOKEN_CONTRACT_MAP, &nft_contract_hash.to_string(), false);
+        if let Some(mut collection) = existing_collection {
+            collection.enabled = false;
+            collections::write_collection(&nft_contract_hash, collection);
+        }
     }
     Ok(())
 }
 
-#[no_mangle]
-pub extern "C" fn transfer_owner() -> Result<(), Error> {
-    let new_contract_owner: Key = runtime::get_named_arg(ARG_CONTRACT_OWNER);
-    // let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
+// Collection creator or market owner, the accounts that curate who can sell a collection
+fn check_collection_admin(nft_contract_hash: &Key) -> Key {
+    let collection = get_collection(nft_contract_hash);
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != collection.creator && caller != current_contract_owner {
+        runtime::revert(Error::InvalidCollectionAdmin);
+    }
+    caller
+}
+
+#[no_mangle]
+pub extern "C" fn set_seller_allowlist_mode() -> Result<(), Error> {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let enabled: bool = runtime::get_named_arg(ARG_SELLER_ALLOWLIST);
+    let caller = check_collection_admin(&nft_contract_hash);
+
+    collections::set_seller_allowlist_enabled(&nft_contract_hash, enabled);
+    events::emit(events::SellerAllowlistModeChanged {
+        collection: nft_contract_hash,
+        enabled,
+        changed_by: caller,
+    });
+    Ok(())
+}
+
+// Approves or removes sellers of a collection, listings already made are left alone
+#[no_mangle]
+pub extern "C" fn set_approved_sellers() -> Result<(), Error> {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let sellers: Vec<Key> = runtime::get_named_arg(ARG_SELLERS);
+    let approved: bool = runtime::get_named_arg(ARG_APPROVED);
+    let caller = check_collection_admin(&nft_contract_hash);
+
+    for seller in sellers {
+        collections::set_approved_seller(&nft_contract_hash, &seller, approved);
+        events::emit(events::SellerAllowlistUpdated {
+            collection: nft_contract_hash,
+            seller,
+            approved,
+            changed_by: caller,
+        });
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn is_approved_seller() {
+    let nft_contract_hash: Key = runtime::get_named_arg(ARG_NFT_CONTRACT_HASH);
+    let seller: Key = runtime::get_named_arg(ARG_SELLER);
+
+    let approved = collections::is_approved_seller(&nft_contract_hash, &seller);
+    let return_value = CLValue::from_t(approved).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Pauses listing, bidding and settlement, for one collection or for the whole market when no
+// collection is given. Flags replace the ones set before, zero resumes everything.
+#[no_mangle]
+pub extern "C" fn set_pause() -> Result<(), Error> {
+    let nft_contract_hash: Option<Key> = get_optional_named_arg(ARG_NFT_CONTRACT_HASH);
+    let pause_flags: u8 = runtime::get_named_arg(ARG_PAUSE_FLAGS);
+
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = helpers::get_verified_caller().unwrap_or_revert();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    pause::set_pause(nft_contract_hash, pause_flags);
+    events::emit(events::PauseChanged {
+        collection: nft_contract_hash,
+        pause_flags,
+        changed_by: caller,
+    });
+    Ok(())
+}
+
+// Flags in effect for a collection, or the market wide ones when no collection is given
+#[no_mangle]
+pub extern "C" fn get_pause() {
+    let nft_contract_hash: Option<Key> = get_optional_named_arg(ARG_NFT_CONTRACT_HASH);
+    let pause_flags: u8 = match nft_contract_hash {
+        Some(nft_contract_hash) => pause::get_effective_pause(&nft_contract_hash),
+        None => pause::get_market_pause(),
+    };
+    let return_value =
+        CLValue::from_t(pause_flags).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Supported collections with their registry record, in the order they were added
+#[no_mangle]
+pub extern "C" fn get_collections() {
+    let offset: u64 = runtime::get_named_arg(ARG_OFFSET);
+    let limit: u64 = runtime::get_named_arg(ARG_LIMIT);
+
+    // Collections the storage migration hasn't registered yet are left out
+    let token_list = get_key::<Vec<Key>>(TOKEN_CONTRACT_LIST).unwrap_or_revert();
+    let page: Vec<(Key, Collection)> = token_list
+        .iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .filter_map(|nft_contract_hash| {
+            collections::read_collection(nft_contract_hash)
+                .map(|collection| (*nft_contract_hash, collection))
+        })
+        .collect();
+
+    let return_value = CLValue::from_t(page).unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+#[no_mangle]
+pub extern "C" fn transfer_owner() -> Result<(), Error> {
+    let new_contract_owner: Key = runtime::get_named_arg(ARG_CONTRACT_OWNER);
+    // let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
     let current_contract_owner = helpers::get_stored_value_with_user_errors(
         CONTRACT_OWNER_KEY_NAME,
         Error::MissingContractOwner,
@@ -664,30 +1365,47 @@ pub extern "C" fn set_fee_receiver() -> Result<(), Error> {
 
 #[no_mangle]
 pub extern "C" fn change_fee() -> Result<(), Error> {
-    let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
     // let caller = get_immediate_caller_key();
     let caller = helpers::get_verified_caller().unwrap_or_revert();
     if caller != current_contract_owner {
         runtime::revert(Error::InvalidContractOwner);
     }
-    let new_fee: U256 = runtime::get_named_arg(MARKET_FEE);
-    if new_fee > U256::from("20") {
+    // Callers from before the basis-point model set one fee, out of 1000, for both sides
+    let (new_buyer_fee, new_seller_fee): (U256, U256) =
+        match get_optional_named_arg::<U256>(ARG_MARKET_FEE) {
+            Some(market_fee) => (market_fee * U256::from("10"), market_fee * U256::from("10")),
+            None => (
+                runtime::get_named_arg(ARG_BUYER_FEE),
+                runtime::get_named_arg(ARG_SELLER_FEE),
+            ),
+        };
+    if new_buyer_fee > U256::from(MAX_MARKET_FEE) || new_seller_fee > U256::from(MAX_MARKET_FEE) {
         runtime::revert(Error::FeeTooHigh);
     }
-    set_key(MARKET_FEE, new_fee);
+    set_key(BUYER_FEE, new_buyer_fee);
+    set_key(SELLER_FEE, new_seller_fee);
     Ok(())
 }
 
 #[no_mangle]
 pub extern "C" fn change_royalty_fee() -> Result<(), Error> {
-    let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
     // let caller = get_immediate_caller_key();
     let caller = helpers::get_verified_caller().unwrap_or_revert();
     if caller != current_contract_owner {
         runtime::revert(Error::InvalidContractOwner);
     }
     let new_royalty_fee: U256 = runtime::get_named_arg(ARG_ROYALTY_FEE);
-    if new_royalty_fee > U256::from("10") {
+    if new_royalty_fee > U256::from(MAX_ROYALTY_FEE) {
         runtime::revert(Error::FeeTooHigh);
     }
     set_key(ROYALTY_FEE, new_royalty_fee);
@@ -718,59 +1436,247 @@ pub extern "C" fn change_is_royalty() -> Result<(), Error> {
     Ok(())
 }
 
-fn get_token_owner(
-    contract_hash: &Key,
-    identifier_mode: &NFTIdentifierMode,
-    token_identifier: &TokenIdentifier,
-) -> Key {
-    let contract_hash_addr: HashAddr = contract_hash.into_hash().unwrap_or_revert();
-    let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
+#[no_mangle]
+pub extern "C" fn set_referral_share() -> Result<(), Error> {
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = helpers::get_verified_caller().unwrap_or_revert();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+    // Portion of the market fee, in basis points, that is paid to the referrer of a trade
+    let new_referral_share: U256 = runtime::get_named_arg(ARG_REFERRAL_SHARE);
+    if new_referral_share > U256::from(FEE_DIVISOR) {
+        runtime::revert(Error::ReferralShareTooHigh);
+    }
+    set_key(REFERRAL_SHARE, new_referral_share);
+    Ok(())
+}
 
-    match *identifier_mode {
-        NFTIdentifierMode::Ordinal => runtime::call_contract::<Key>(
-            contract_hash,
-            ENTRY_POINT_OWNER_OF,
-            runtime_args! {
-                ARG_TOKEN_ID => token_identifier.clone().get_index().unwrap()
-            },
-        ),
-        NFTIdentifierMode::Hash => runtime::call_contract::<Key>(
-            contract_hash,
-            ENTRY_POINT_OWNER_OF,
-            runtime_args! {
-                ARG_TOKEN_HASH =>
-                token_identifier.clone().get_hash().unwrap()
-            },
-        ),
+#[no_mangle]
+pub extern "C" fn set_fee_discount() -> Result<(), Error> {
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = helpers::get_verified_caller().unwrap_or_revert();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+    let staking_contract: Key = runtime::get_named_arg(ARG_STAKING_CONTRACT);
+    let staking_pool_id: u64 = runtime::get_named_arg(ARG_STAKING_POOL_ID);
+    let discount_thresholds: Vec<U256> = runtime::get_named_arg(ARG_DISCOUNT_THRESHOLDS);
+    let discount_rates: Vec<U256> = runtime::get_named_arg(ARG_DISCOUNT_RATES);
+
+    if discount_thresholds.len() != discount_rates.len() {
+        runtime::revert(Error::InvalidDiscountTiers);
     }
+    // Thresholds must be strictly ascending so the highest reached tier wins
+    for i in 1..discount_thresholds.len() {
+        if discount_thresholds[i] <= discount_thresholds[i - 1] {
+            runtime::revert(Error::InvalidDiscountTiers);
+        }
+    }
+    if discount_rates.iter().any(|rate| *rate > U256::from(FEE_DIVISOR)) {
+        runtime::revert(Error::DiscountTooHigh);
+    }
+    // Trades call this contract for every discount, it has to be a contract hash
+    if staking_contract.into_hash().is_none() {
+        runtime::revert(Error::InvalidStakingContract);
+    }
+
+    // Contract and pool are always written together, get_fee_discounts relies on both
+    set_key(STAKING_CONTRACT, staking_contract);
+    set_key(STAKING_POOL_ID, staking_pool_id);
+    set_key(DISCOUNT_THRESHOLDS, discount_thresholds);
+    set_key(DISCOUNT_RATES, discount_rates);
+    Ok(())
 }
 
-// this fn to get CasperPunk Creator to do royalty things
-fn get_token_creator(
-    contract_hash: &Key,
-    identifier_mode: &NFTIdentifierMode,
-    token_identifier: &TokenIdentifier,
-) -> Key {
-    let csp_contract_hash_addr: HashAddr = contract_hash.into_hash().unwrap_or_revert();
-    let csp_contract_hash: ContractHash = ContractHash::new(csp_contract_hash_addr);
+// Discounts, in basis points, on the market fee for each trader, from the stake it keeps locked
+// in the configured pool. Every trader is looked up in a single call to the staking contract.
+// A discount that isn't fully configured gives no discount, so it never blocks a trade.
+fn get_fee_discounts(traders: Vec<Key>) -> Vec<U256> {
+    let no_discounts: Vec<U256> = traders.iter().map(|_| U256::zero()).collect();
+    let contract_hash_addr: HashAddr = match get_key::<Key>(STAKING_CONTRACT)
+        .and_then(|staking_contract| staking_contract.into_hash())
+    {
+        Some(contract_hash_addr) => contract_hash_addr,
+        None => return no_discounts,
+    };
+    let staking_pool_id: u64 = match get_key::<u64>(STAKING_POOL_ID) {
+        Some(staking_pool_id) => staking_pool_id,
+        None => return no_discounts,
+    };
+    let discount_thresholds: Vec<U256> = get_key(DISCOUNT_THRESHOLDS).unwrap_or_default();
+    if discount_thresholds.is_empty() {
+        return no_discounts;
+    }
+    let discount_rates: Vec<U256> = get_key(DISCOUNT_RATES).unwrap_or_default();
 
-    match *identifier_mode {
-        NFTIdentifierMode::Ordinal => runtime::call_contract::<Key>(
-            csp_contract_hash,
-            "get_token_creator",
-            runtime_args! {
-                ARG_TOKEN_ID => token_identifier.clone().get_index().unwrap()
-            },
-        ),
-        NFTIdentifierMode::Hash => runtime::call_contract::<Key>(
-            csp_contract_hash,
-            "get_token_creator",
-            runtime_args! {
-                ARG_TOKEN_HASH =>
-                token_identifier.clone().get_hash().unwrap()
-            },
-        ),
+    // Only locked stake counts, stake that can leave right after the trade would game the discount
+    let staking_contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
+    let locked_stakes: Vec<U256> = runtime::call_contract(
+        staking_contract_hash,
+        GET_LOCKED_STAKES_ENTRY_POINT_NAME,
+        runtime_args! {
+            ARG_POOL_ID => staking_pool_id,
+            ARG_USERS => traders,
+        },
+    );
+
+    locked_stakes
+        .iter()
+        .map(|locked_stake| {
+            let mut discount = U256::zero();
+            for (threshold, rate) in discount_thresholds.iter().zip(discount_rates.iter()) {
+                if *locked_stake >= *threshold {
+                    discount = *rate;
+                }
+            }
+            discount
+        })
+        .collect()
+}
+
+// Moves a contract installed before fee version 1 to basis points. The first call converts the
+// stored rates, every call moves the given token markets to the current fee version.
+#[no_mangle]
+pub extern "C" fn migrate_fee_model() -> Result<(), Error> {
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = helpers::get_verified_caller().unwrap_or_revert();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
     }
+
+    // Old rates were out of 1000, and the market fee was paid by both sides. The referral share
+    // and the discount rates only ever existed in basis points, they stay as they are.
+    if get_key::<u8>(FEE_VERSION).unwrap_or_default() < CURRENT_FEE_VERSION {
+        let market_fee: U256 = get_key(MARKET_FEE).unwrap_or_default();
+        let royalty_fee: U256 = get_key(ROYALTY_FEE).unwrap_or_default();
+        set_key(BUYER_FEE, market_fee * U256::from("10"));
+        set_key(SELLER_FEE, market_fee * U256::from("10"));
+        set_key(ROYALTY_FEE, royalty_fee * U256::from("10"));
+        if runtime::get_key(CLAIMABLE_BALANCES).is_none() {
+            storage::new_dictionary(CLAIMABLE_BALANCES)
+                .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+        }
+        set_key(FEE_VERSION, CURRENT_FEE_VERSION);
+    }
+
+    // MARKET_FEE keeps the per-mille fee that legacy bids escrowed on top of the bid
+    let legacy_market_fee: U256 = get_key(MARKET_FEE).unwrap_or_default();
+    let token_market_keys: Vec<String> = runtime::get_named_arg(ARG_TOKEN_MARKET_KEYS);
+    for token_market_key in token_market_keys {
+        let mut token_market = match get_token_market_by_key(&token_market_key) {
+            Some(token_market) => token_market,
+            None => continue,
+        };
+        if token_market.fee_version >= CURRENT_FEE_VERSION {
+            continue;
+        }
+        // The escrow held the bid and its market fee, the fee is kept as locked_fee so settling
+        // or refunding the bid accounts for it
+        token_market.locked_fee = if token_market.bidder.is_some() {
+            token_market.locked_bid * legacy_market_fee / U256::from("1000")
+        } else {
+            U256::zero()
+        };
+        token_market.fee_version = CURRENT_FEE_VERSION;
+        write_token_market(&token_market_key, token_market);
+    }
+    Ok(())
+}
+
+// Rewrites a page of token markets still stored as JSON strings with their bytesrepr encoding, and
+// adds a page of token markets listed before the indexes to them. Already converted or indexed
+// token markets are skipped, so pages can be retried.
+#[no_mangle]
+pub extern "C" fn migrate_storage() -> Result<(), Error> {
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = helpers::get_verified_caller().unwrap_or_revert();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    create_missing_storage();
+
+    let token_market_keys: Vec<String> = runtime::get_named_arg(ARG_TOKEN_MARKET_KEYS);
+    for token_market_key in token_market_keys {
+        if let Some(token_market) = get_legacy_token_market(&token_market_key) {
+            write_token_market(&token_market_key, token_market);
+        }
+    }
+
+    // Entries are (nft_contract_hash, token_id) as the indexes store them
+    let index_entries: Vec<IndexEntry> =
+        get_optional_named_arg(ARG_INDEX_ENTRIES).unwrap_or_default();
+    for (nft_contract_hash, token_id) in index_entries {
+        let collection = get_collection(&nft_contract_hash);
+        let token_identifier = nft::parse_token_identifier(&collection, &token_id);
+        let token_market_key = get_token_market_key(&nft_contract_hash, &token_identifier);
+        let token_market = match get_token_market_by_key(&token_market_key) {
+            Some(token_market) => token_market,
+            None => continue,
+        };
+        if !token_market.is_active.unwrap_or(false) {
+            continue;
+        }
+        if let Some(offeror) = token_market.offeror {
+            add_to_index(
+                LISTINGS_BY_COLLECTION,
+                &nft_contract_hash,
+                &nft_contract_hash,
+                &token_identifier,
+            );
+            add_to_index(LISTINGS_BY_SELLER, &offeror, &nft_contract_hash, &token_identifier);
+        }
+        if let Some(bidder) = token_market.bidder {
+            add_to_index(BIDS_BY_BIDDER, &bidder, &nft_contract_hash, &token_identifier);
+        }
+    }
+    Ok(())
+}
+
+// Fees for a sale at `price`, shared by every entry point that escrows or settles a trade
+fn get_sale_fees(buyer: Key, seller: Key, price: U256, collection: &Collection) -> FeeBreakdown {
+    let buyer_fee: U256 = helpers::get_stored_value_with_user_errors(
+        BUYER_FEE,
+        Error::MissingFeePortion,
+        Error::InvalidFeePortion,
+    );
+    let seller_fee: U256 = helpers::get_stored_value_with_user_errors(
+        SELLER_FEE,
+        Error::MissingFeePortion,
+        Error::InvalidFeePortion,
+    );
+    let is_royalty: bool = helpers::get_stored_value_with_user_errors(
+        IS_ROYALTY,
+        Error::MissingIsRoyalty,
+        Error::InvalidIsRoyalty,
+    );
+    let royalty_fee: U256 = if is_royalty { collection.royalty_fee } else { U256::zero() };
+
+    let discounts: Vec<U256> = get_fee_discounts(vec![buyer, seller]);
+
+    FeeBreakdown::new(
+        price,
+        fees::discounted_bps(buyer_fee, discounts[0]),
+        fees::discounted_bps(seller_fee, discounts[1]),
+        royalty_fee,
+    )
 }
 
 fn get_token_metadata(
@@ -802,40 +1708,36 @@ fn get_token_metadata(
 
 fn set_offer(
     contract_hash: &Key,
-    identifier_mode: &NFTIdentifierMode,
     token_identifier: &TokenIdentifier,
     offeror: Option<Key>,
     minimum_offer: U256,
 ) {
     let token_market_key = get_token_market_key(contract_hash, token_identifier); // Key for TOKEN_MARKET dictionary
                                                                                   // Check if this token is already been offered
-    let token_market_str = get_dictionary_value_from_key::<String>(TOKEN_MARKET, &token_market_key);
-    if token_market_str.is_some() {
-        let token_market =
-            casper_serde_json_wasm::from_str::<TokenMarket>(&token_market_str.unwrap());
-        if token_market.is_ok() {
-            let token_market_unwrap = token_market.unwrap();
-            if token_market_unwrap.is_active.unwrap() == true {
-                runtime::revert(Error::AlreadyMakeOffer);
-            }
+    let token_market = get_token_market_by_key(&token_market_key);
+    if token_market.is_some() {
+        let token_market_unwrap = token_market.unwrap();
+        if token_market_unwrap.is_active.unwrap() == true {
+            runtime::revert(Error::AlreadyMakeOffer);
         }
     }
 
-    write_dictionary_value_from_key(
-        TOKEN_MARKET,
-        &token_market_key,
-        casper_serde_json_wasm::to_string_pretty(&TokenMarket {
-            offeror: offeror,
-            minimum_offer: minimum_offer,
-            bidder: None,
-            locked_bid: U256::zero(),
-            is_active: Some(true),
-        })
-        .unwrap(),
-    );
-    events::emit(&MarketPlaceEvent::Offer {
-        token_market_key: token_market_key,
-        offeror: offeror.unwrap(),
+    write_token_market(&token_market_key, TokenMarket {
+        offeror: offeror,
+        minimum_offer: minimum_offer,
+        bidder: None,
+        locked_bid: U256::zero(),
+        is_active: Some(true),
+        locked_fee: U256::zero(),
+        fee_version: CURRENT_FEE_VERSION,
+        referrer: None,
+    });
+    add_to_index(LISTINGS_BY_COLLECTION, contract_hash, contract_hash, token_identifier);
+    add_to_index(LISTINGS_BY_SELLER, &offeror.unwrap(), contract_hash, token_identifier);
+    events::emit(events::ListingCreated {
+        collection: *contract_hash,
+        token_id: token_identifier.to_string(),
+        seller: offeror.unwrap(),
         minimum_offer: minimum_offer,
     });
 }
@@ -844,102 +1746,68 @@ fn do_trade(
     token_market_key: String,
     nft_contract_hash: &Key,
     token_id: &TokenIdentifier,
-    identifier_mode: &NFTIdentifierMode,
+    collection: &Collection,
     offeror: Key,
     bidder: Key,
     value: U256,         // cspr value
+    escrowed: U256,      // already held by the market for the bidder
     wcspr_contract: Key, // ERC20 token contract
+    referrer: Option<Key>,
 ) {
+    pause::require_settlement_open(nft_contract_hash);
+
     let market_fee_receiver: Key = helpers::get_stored_value_with_user_errors(
         MARKET_FEE_RECEIVER,
         Error::MissingFeeReceiver,
         Error::InvalidFeeReceiver,
     );
 
-    let royalty_fee: U256 = helpers::get_stored_value_with_user_errors(
-        ROYALTY_FEE,
-        Error::MissingRoyaltyFee,
-        Error::InvalidRoyaltyFee,
-    );
-
-    let is_royalty: bool = helpers::get_stored_value_with_user_errors(
-        IS_ROYALTY,
-        Error::MissingIsRoyalty,
-        Error::InvalidIsRoyalty,
-    );
-
-    // pay FEE to contract of CSPR.
+    let fees = get_sale_fees(bidder, offeror, value, collection);
 
-    let fee_portion: U256 = helpers::get_stored_value_with_user_errors(
-        MARKET_FEE,
-        Error::MissingFeePortion,
-        Error::InvalidFeePortion,
-    );
-
-    let trade_fee: U256 = value * fee_portion / U256::from("1000");
-    let total_contract_receive: U256 = trade_fee * U256::from("2");
-
-    // let nft_creator: Key = get_token_creator(
-    //     nft_contract_hash: &Key,
-    //     identifier_mode: &NFTIdentifierMode,
-    //     token_id: &TokenIdentifier,
-    // );
-
-    let royalty_amount: U256 = (value - trade_fee) * royalty_fee / U256::from("1000");
-    let seller_amount: U256 = value - trade_fee - royalty_amount;
+    // Referrer takes its share out of the market fee, the rest stays with the fee receiver
+    check_referrer(referrer, bidder, offeror);
+    let referral_amount: U256 = match referrer {
+        Some(_) => {
+            let referral_share: U256 = get_key::<U256>(REFERRAL_SHARE).unwrap_or_default();
+            fees::apply_bps(fees.market_fee(), referral_share)
+        }
+        None => U256::zero(),
+    };
 
     let contract_hash_addr: HashAddr = wcspr_contract.into_hash().unwrap_or_revert();
     let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
-    // Transfer fee to contract
-    let _: () = runtime::call_contract(
-        contract_hash, // wcspr contract
-        TRANSFER_FROM_ENTRY_POINT_NAME,
-        runtime_args! {
-            "owner" => bidder,
-            "recipient" => market_fee_receiver,
-            "amount" => total_contract_receive,
-        },
-    );
-
-    // Check if is_royalty is true then transfer ROYALTY_FEE to CREATOR
-
-    if is_royalty {
-        // get creator of nft
-        let nft_creator : Key = get_token_creator(nft_contract_hash: &Key, identifier_mode: &NFTIdentifierMode, token_id: &TokenIdentifier);
-
-        let _: () = runtime::call_contract(
-            contract_hash, // wcspr contract
-            TRANSFER_FROM_ENTRY_POINT_NAME,
-            runtime_args! {
-                "owner" => bidder,
-                "recipient" => nft_creator,
-                "amount" => royalty_amount,
-            },
-        );
-
-        // Transfer wcspr to seller
+    // Pull whatever the escrow doesn't cover, every party withdraws its share later
+    if fees.buyer_total() > escrowed {
         let _: () = runtime::call_contract(
             contract_hash, // wcspr contract
             TRANSFER_FROM_ENTRY_POINT_NAME,
             runtime_args! {
                 "owner" => bidder,
-                "recipient" => offeror,
-                "amount" => seller_amount,
+                "recipient" => get_self_key(),
+                "amount" => fees.buyer_total() - escrowed,
             },
         );
     } else {
-        // Transfer wcspr to seller
-        let _: () = runtime::call_contract(
-            contract_hash, // wcspr contract
-            TRANSFER_FROM_ENTRY_POINT_NAME,
-            runtime_args! {
-                "owner" => bidder,
-                "recipient" => offeror,
-                "amount" => value - trade_fee,
-            },
-        );
+        credit_claimable_balance(bidder, wcspr_contract, escrowed - fees.buyer_total());
+    }
+
+    credit_claimable_balance(
+        market_fee_receiver,
+        wcspr_contract,
+        fees.market_fee() - referral_amount,
+    );
+
+    if referral_amount > U256::zero() {
+        let referrer = referrer.unwrap();
+        credit_claimable_balance(referrer, wcspr_contract, referral_amount);
     }
 
+    // Royalty is only charged while is_royalty is on, it goes to the collection creator
+    if fees.royalty_amount > U256::zero() {
+        credit_claimable_balance(collection.creator, wcspr_contract, fees.royalty_amount);
+    }
+    credit_claimable_balance(offeror, wcspr_contract, fees.seller_amount());
+
     // let src_purse: URef = runtime::get_named_arg("src_purse");
 
     // let contract_purse_key = runtime::get_key("contract_purse").unwrap_or_revert();
@@ -965,71 +1833,79 @@ fn do_trade(
     // .unwrap_or_revert();
 
     // transfer_from nft from offeror to bidder
-    let identifier_mode = get_identifier_mode_from_runtime_args();
-    let token_identifier = get_token_identifier_from_runtime_args(&identifier_mode);
-
-    cep78_transfer_from(
+    nft::transfer_from(
         nft_contract_hash: &Key,
+        collection: &Collection,
         offeror: Key,
         bidder: Key,
-        identifier_mode: NFTIdentifierMode,
-        token_identifier: TokenIdentifier,
+        token_id.clone(): TokenIdentifier,
     );
 
-    write_dictionary_value_from_key(
-        TOKEN_MARKET,
-        &token_market_key,
-        casper_serde_json_wasm::to_string_pretty(&TokenMarket {
-            offeror: None,
-            minimum_offer: U256::zero(),
-            bidder: None,
-            locked_bid: U256::zero(),
-            is_active: Some(false),
-        })
-        .unwrap(),
-    );
-
-    events::emit(&MarketPlaceEvent::DoTrade {
-        from: offeror,
-        to: bidder,
-        value: value,
+    write_token_market(&token_market_key, TokenMarket {
+        offeror: None,
+        minimum_offer: U256::zero(),
+        bidder: None,
+        locked_bid: U256::zero(),
+        is_active: Some(false),
+        locked_fee: U256::zero(),
+        fee_version: CURRENT_FEE_VERSION,
+        referrer: None,
+    });
+    remove_from_indexes(nft_contract_hash, token_id, offeror, Some(bidder));
+    stats::record_sale(nft_contract_hash, &token_market_key, value);
+
+    events::emit(events::TradeSettled {
+        collection: *nft_contract_hash,
+        token_id: token_id.to_string(),
+        seller: offeror,
+        buyer: bidder,
+        currency: wcspr_contract,
+        price: value,
+        market_fee: fees.market_fee(),
+        royalty_recipient: if fees.royalty_amount > U256::zero() {
+            Some(collection.creator)
+        } else {
+            None
+        },
+        royalty_amount: fees.royalty_amount,
+        referrer: if referral_amount > U256::zero() { referrer } else { None },
+        referral_amount: referral_amount,
     });
 }
 
-fn cep78_transfer_from(
-    contract_hash: &Key,
-    source: Key,
-    target: Key,
-    identifier_mode: NFTIdentifierMode,
-    token_identifier: TokenIdentifier,
+// Drops a token market that is no longer listed from the indexes of its collection, seller and bidder
+fn remove_from_indexes(
+    nft_contract_hash: &Key,
+    token_identifier: &TokenIdentifier,
+    offeror: Key,
+    bidder: Option<Key>,
 ) {
-    let contract_hash_addr: HashAddr = contract_hash.into_hash().unwrap_or_revert();
-    let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
-    match identifier_mode {
-        NFTIdentifierMode::Ordinal => {
-            let _: (String, Key) = runtime::call_contract(
-                contract_hash,
-                TRANSFER_ENTRY_POINT_NAME,
-                runtime_args! {
-                    ARG_SOURCE_KEY => source,
-                    ARG_TARGET_KEY => target,
-                    ARG_TOKEN_ID => token_identifier.get_index().unwrap()
-                },
-            );
-        }
-        NFTIdentifierMode::Hash => {
-            let _: (String, Key) = runtime::call_contract(
-                contract_hash,
-                TRANSFER_ENTRY_POINT_NAME,
-                runtime_args! {
-                    ARG_SOURCE_KEY => source,
-                    ARG_TARGET_KEY => target,
-                    ARG_TOKEN_HASH => token_identifier.get_hash().unwrap()
-                },
-            );
+    remove_from_index(LISTINGS_BY_COLLECTION, nft_contract_hash, nft_contract_hash, token_identifier);
+    remove_from_index(LISTINGS_BY_SELLER, &offeror, nft_contract_hash, token_identifier);
+    if let Some(bidder) = bidder {
+        remove_from_index(BIDS_BY_BIDDER, &bidder, nft_contract_hash, token_identifier);
+    }
+}
+
+// A trader can't refer its own trade, neither from the buying nor from the selling side
+fn check_referrer(referrer: Option<Key>, bidder: Key, offeror: Key) {
+    if let Some(referrer) = referrer {
+        if referrer == bidder || referrer == offeror {
+            runtime::revert(Error::InvalidReferrer);
         }
     }
 }
+
+fn credit_claimable_balance(account: Key, currency: Key, amount: U256) {
+    if amount == U256::zero() {
+        return;
+    }
+    let claimable_balance_key = get_claimable_balance_key(&account, &currency);
+    let balance: U256 =
+        get_dictionary_value_from_key(CLAIMABLE_BALANCES, &claimable_balance_key).unwrap_or_default();
+    write_dictionary_value_from_key(CLAIMABLE_BALANCES, &claimable_balance_key, balance + amount);
+}
+
 fn update_new_bidder(
     token_market_key: String,
     bidding_offer: U256,
@@ -1037,20 +1913,20 @@ fn update_new_bidder(
     new_bidder: Key,
     wcspr_contract: Key, // ERC20 token contract
     contract_self_key: Key,
+    nft_contract_hash: &Key,
+    token_identifier: &TokenIdentifier,
+    collection: &Collection,
+    referrer: Option<Key>,
     // new_bidder_addr : Address
 ) {
-    let market_fee_receiver: Key = helpers::get_stored_value_with_user_errors(
-        MARKET_FEE_RECEIVER,
-        Error::MissingFeeReceiver,
-        Error::InvalidFeeReceiver,
-    );
+    let offeror: Key = token_market
+        .offeror
+        .unwrap_or_revert_with(Error::MissingOfferer);
+    check_referrer(referrer, new_bidder, offeror);
 
-    let fee_portion: U256 = helpers::get_stored_value_with_user_errors(
-        MARKET_FEE,
-        Error::MissingFeePortion,
-        Error::InvalidFeePortion,
-    );
-    let needed_amount: U256 = bidding_offer + bidding_offer * fee_portion / U256::from("1000");
+    // Escrow the bid together with the buyer fee it will owe on settlement
+    let fees = get_sale_fees(new_bidder, offeror, bidding_offer, collection);
+    let needed_amount: U256 = fees.buyer_total();
 
     // Transfer CSPR
     // let new_src_purse: URef = runtime::get_named_arg(ARG_SRC_PURSE);
@@ -1079,9 +1955,6 @@ fn update_new_bidder(
     //     )
     //     .unwrap_or_revert();
     // };
-    let offeror: Key = token_market
-        .offeror
-        .unwrap_or_revert_with(Error::MissingOfferer);
 
     let contract_hash_addr: HashAddr = wcspr_contract.into_hash().unwrap_or_revert();
     let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
@@ -1092,53 +1965,45 @@ fn update_new_bidder(
         runtime_args! {
             "owner" => new_bidder,
             "recipient" => contract_self_key,
-            "amount" => bidding_offer,
+            "amount" => needed_amount,
         },
     );
 
+    // The outbid bidder withdraws its escrow later, a failing recipient can't block the new bid
     if token_market.bidder.is_some() && (token_market.locked_bid != U256::zero()) {
         let older_bidder_account: Key = token_market.bidder.unwrap();
-        let _: () = runtime::call_contract(
-            contract_hash,
-            TRANSFER_ENTRY_POINT_NAME,
-            runtime_args! {
-                "recipient" => older_bidder_account,
-                "amount" => token_market.locked_bid,
-            },
+        credit_claimable_balance(
+            older_bidder_account,
+            wcspr_contract,
+            token_market.locked_bid + token_market.locked_fee,
         );
+        events::emit(events::BidRefunded {
+            collection: *nft_contract_hash,
+            token_id: token_identifier.to_string(),
+            bidder: older_bidder_account,
+            currency: wcspr_contract,
+            amount: token_market.locked_bid + token_market.locked_fee,
+        });
     };
 
     //Update dictionary for token_maket_key
 
-    write_dictionary_value_from_key(
-        TOKEN_MARKET,
-        &token_market_key,
-        casper_serde_json_wasm::to_string_pretty(&TokenMarket {
-            offeror: token_market.offeror,
-            minimum_offer: token_market.minimum_offer,
-            bidder: Some(new_bidder),
-            locked_bid: bidding_offer,
-            is_active: Some(true),
-        })
-        .unwrap(),
-    );
+    write_token_market(&token_market_key, TokenMarket {
+        offeror: token_market.offeror,
+        minimum_offer: token_market.minimum_offer,
+        bidder: Some(new_bidder),
+        locked_bid: bidding_offer,
+        is_active: Some(true),
+        locked_fee: fees.buyer_fee,
+        fee_version: CURRENT_FEE_VERSION,
+        referrer: referrer,
+    });
 
-    events::emit(&MarketPlaceEvent::Bid {
-        token_market_key: token_market_key,
-        offeror: token_market.offeror.unwrap(),
+    events::emit(events::BidPlaced {
+        collection: *nft_contract_hash,
+        token_id: token_identifier.to_string(),
         bidder: new_bidder,
-        value: bidding_offer,
+        currency: wcspr_contract,
+        amount: bidding_offer,
     });
 }
-fn check_enabled_nft(contract_hash: Key) {
-    let nft_contract_str_key = helpers::make_dictionary_item_key_for_key(contract_hash);
-
-    let enabled = get_dictionary_value_from_key::<bool>(
-        TOKEN_CONTRACT_MAP,
-        &nft_contract_str_key.to_string(),
-    )
-    .unwrap();
-    if !enabled {
-        runtime::revert(Error::UnsupportedToken);
-    }
-}
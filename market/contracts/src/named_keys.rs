@@ -10,7 +10,6 @@ use crate::constants::*;
 pub fn default(
     contract_owner: Key,
     market_fee_receiver: Key,
-    market_fee: U256,
     contract_package_hash : ContractPackageHash,
     royalty_fee : U256,
     fee_token: Option<Key>,
@@ -0,0 +1,156 @@
+//! Adapter over the NFT standards the market can trade.
+//!
+//! Every registered collection says which standard it implements, entry points go through these
+//! functions instead of calling the NFT contract directly.
+use alloc::{string::String, vec};
+use casper_contract::{contract_api::runtime, unwrap_or_revert::UnwrapOrRevert};
+use casper_types::{runtime_args, ContractHash, HashAddr, Key, RuntimeArgs, U256};
+use core::convert::TryFrom;
+
+use crate::collections::Collection;
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{self, get_named_arg_with_user_errors, NFTIdentifierMode, TokenIdentifier};
+
+#[repr(u8)]
+#[derive(Copy, Clone, PartialEq)]
+pub enum NFTStandard {
+    CEP78 = 0,
+    CEP47 = 1,
+}
+
+impl TryFrom<u8> for NFTStandard {
+    type Error = Error;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(NFTStandard::CEP78),
+            1 => Ok(NFTStandard::CEP47),
+            _ => Err(Error::InvalidNFTStandard),
+        }
+    }
+}
+
+/// Token identifier in the form the collection's standard uses, from its string form as stored in
+/// the indexes.
+pub(crate) fn parse_token_identifier(collection: &Collection, token_id: &str) -> TokenIdentifier {
+    match collection.standard() {
+        NFTStandard::CEP78 => match collection.identifier_mode() {
+            NFTIdentifierMode::Ordinal => token_id
+                .parse::<u64>()
+                .ok()
+                .map(TokenIdentifier::new_index)
+                .unwrap_or_revert_with(Error::InvalidTokenIdentifier),
+            NFTIdentifierMode::Hash => TokenIdentifier::new_hash(String::from(token_id)),
+        },
+        NFTStandard::CEP47 => U256::from_dec_str(token_id)
+            .ok()
+            .map(TokenIdentifier::new_token_id)
+            .unwrap_or_revert_with(Error::InvalidTokenIdentifier),
+    }
+}
+
+/// Reads the token identifier in the form the collection's standard uses.
+pub(crate) fn get_token_identifier_from_runtime_args(collection: &Collection) -> TokenIdentifier {
+    match collection.standard() {
+        NFTStandard::CEP78 => {
+            helpers::get_token_identifier_from_runtime_args(&collection.identifier_mode())
+        }
+        NFTStandard::CEP47 => get_named_arg_with_user_errors::<U256>(
+            ARG_TOKEN_ID,
+            Error::MissingTokenID,
+            Error::InvalidTokenIdentifier,
+        )
+        .map(TokenIdentifier::new_token_id)
+        .unwrap_or_revert(),
+    }
+}
+
+pub(crate) fn get_token_owner(
+    nft_contract_hash: &Key,
+    collection: &Collection,
+    token_identifier: &TokenIdentifier,
+) -> Key {
+    let contract_hash = get_contract_hash(nft_contract_hash);
+
+    match collection.standard() {
+        NFTStandard::CEP78 => match collection.identifier_mode() {
+            NFTIdentifierMode::Ordinal => runtime::call_contract::<Key>(
+                contract_hash,
+                ENTRY_POINT_OWNER_OF,
+                runtime_args! {
+                    ARG_TOKEN_ID => token_identifier.get_index().unwrap_or_revert()
+                },
+            ),
+            NFTIdentifierMode::Hash => runtime::call_contract::<Key>(
+                contract_hash,
+                ENTRY_POINT_OWNER_OF,
+                runtime_args! {
+                    ARG_TOKEN_HASH => token_identifier.clone().get_hash().unwrap_or_revert()
+                },
+            ),
+        },
+        // CEP-47 returns None for tokens that were burnt or never minted
+        NFTStandard::CEP47 => runtime::call_contract::<Option<Key>>(
+            contract_hash,
+            ENTRY_POINT_OWNER_OF,
+            runtime_args! {
+                ARG_TOKEN_ID => token_identifier.get_token_id().unwrap_or_revert()
+            },
+        )
+        .unwrap_or_revert_with(Error::MissingTokenOwner),
+    }
+}
+
+/// Moves a token from `source` to `target`, the market must be approved for it.
+pub(crate) fn transfer_from(
+    nft_contract_hash: &Key,
+    collection: &Collection,
+    source: Key,
+    target: Key,
+    token_identifier: TokenIdentifier,
+) {
+    let contract_hash = get_contract_hash(nft_contract_hash);
+
+    match collection.standard() {
+        NFTStandard::CEP78 => match collection.identifier_mode() {
+            NFTIdentifierMode::Ordinal => {
+                let _: (String, Key) = runtime::call_contract(
+                    contract_hash,
+                    TRANSFER_ENTRY_POINT_NAME,
+                    runtime_args! {
+                        ARG_SOURCE_KEY => source,
+                        ARG_TARGET_KEY => target,
+                        ARG_TOKEN_ID => token_identifier.get_index().unwrap_or_revert()
+                    },
+                );
+            }
+            NFTIdentifierMode::Hash => {
+                let _: (String, Key) = runtime::call_contract(
+                    contract_hash,
+                    TRANSFER_ENTRY_POINT_NAME,
+                    runtime_args! {
+                        ARG_SOURCE_KEY => source,
+                        ARG_TARGET_KEY => target,
+                        ARG_TOKEN_HASH => token_identifier.get_hash().unwrap_or_revert()
+                    },
+                );
+            }
+        },
+        NFTStandard::CEP47 => {
+            let _: () = runtime::call_contract(
+                contract_hash,
+                TRANSFER_FROM_ENTRY_POINT_NAME,
+                runtime_args! {
+                    ARG_SENDER => source,
+                    ARG_RECIPIENT => target,
+                    ARG_TOKEN_IDS => vec![token_identifier.get_token_id().unwrap_or_revert()]
+                },
+            );
+        }
+    }
+}
+
+fn get_contract_hash(nft_contract_hash: &Key) -> ContractHash {
+    let contract_hash_addr: HashAddr = nft_contract_hash.into_hash().unwrap_or_revert();
+    ContractHash::new(contract_hash_addr)
+}
@@ -0,0 +1,80 @@
+//! Circuit breakers of the market.
+//!
+//! Listing, bidding and settlement can each be paused for the whole market or for a single
+//! collection, a flag set at either level pauses the action. Exit paths (`revoke_bid`,
+//! `revoke_offer` and `withdraw`) never check these flags, users can always get their escrow back.
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::Key;
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{
+    get_dictionary_value_from_key, get_key, make_dictionary_item_key_for_key, set_key,
+    write_dictionary_value_from_key,
+};
+
+pub(crate) const PAUSE_LISTING: u8 = 0b001;
+pub(crate) const PAUSE_BIDDING: u8 = 0b010;
+pub(crate) const PAUSE_SETTLEMENT: u8 = 0b100;
+pub(crate) const PAUSE_ALL: u8 = PAUSE_LISTING | PAUSE_BIDDING | PAUSE_SETTLEMENT;
+
+/// Creates the storage of the pause flags, if it doesn't exist yet.
+pub(crate) fn create_pause_storage() {
+    if runtime::get_key(MARKET_PAUSE).is_none() {
+        runtime::put_key(MARKET_PAUSE, storage::new_uref(0u8).into());
+    }
+    if runtime::get_key(COLLECTION_PAUSES).is_none() {
+        storage::new_dictionary(COLLECTION_PAUSES)
+            .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    }
+}
+
+/// Flags paused for the whole market.
+pub(crate) fn get_market_pause() -> u8 {
+    get_key::<u8>(MARKET_PAUSE).unwrap_or_default()
+}
+
+/// Flags paused for a single collection, on top of the market wide ones.
+pub(crate) fn get_collection_pause(nft_contract_hash: &Key) -> u8 {
+    let pause_key = make_dictionary_item_key_for_key(*nft_contract_hash);
+    get_dictionary_value_from_key::<u8>(COLLECTION_PAUSES, &pause_key).unwrap_or_default()
+}
+
+pub(crate) fn set_pause(nft_contract_hash: Option<Key>, flags: u8) {
+    if flags & !PAUSE_ALL != 0 {
+        runtime::revert(Error::InvalidPauseFlags);
+    }
+    match nft_contract_hash {
+        Some(nft_contract_hash) => {
+            let pause_key = make_dictionary_item_key_for_key(nft_contract_hash);
+            write_dictionary_value_from_key(COLLECTION_PAUSES, &pause_key, flags);
+        }
+        None => set_key(MARKET_PAUSE, flags),
+    }
+}
+
+/// Flags in effect for a collection, market wide and its own combined.
+pub(crate) fn get_effective_pause(nft_contract_hash: &Key) -> u8 {
+    get_market_pause() | get_collection_pause(nft_contract_hash)
+}
+
+pub(crate) fn require_listing_open(nft_contract_hash: &Key) {
+    if get_effective_pause(nft_contract_hash) & PAUSE_LISTING != 0 {
+        runtime::revert(Error::ListingPaused);
+    }
+}
+
+pub(crate) fn require_bidding_open(nft_contract_hash: &Key) {
+    if get_effective_pause(nft_contract_hash) & PAUSE_BIDDING != 0 {
+        runtime::revert(Error::BiddingPaused);
+    }
+}
+
+pub(crate) fn require_settlement_open(nft_contract_hash: &Key) {
+    if get_effective_pause(nft_contract_hash) & PAUSE_SETTLEMENT != 0 {
+        runtime::revert(Error::SettlementPaused);
+    }
+}
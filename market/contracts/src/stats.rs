@@ -0,0 +1,103 @@
+//! Trading statistics recorded by `do_trade`, a reference price other contracts can rely on.
+use alloc::vec::Vec;
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    bytesrepr::{self, FromBytes, ToBytes},
+    CLType, CLTyped, Key, U256,
+};
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{
+    get_dictionary_value_from_key, make_dictionary_item_key_for_key,
+    write_dictionary_value_from_key,
+};
+
+#[derive(Clone, Default)]
+pub(crate) struct CollectionStats {
+    pub total_volume: U256,
+    pub trade_count: u64,
+    pub last_sale_price: U256,
+    pub all_time_high: U256,
+}
+
+impl CLTyped for CollectionStats {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for CollectionStats {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.total_volume.to_bytes()?);
+        result.append(&mut self.trade_count.to_bytes()?);
+        result.append(&mut self.last_sale_price.to_bytes()?);
+        result.append(&mut self.all_time_high.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.total_volume.serialized_length()
+            + self.trade_count.serialized_length()
+            + self.last_sale_price.serialized_length()
+            + self.all_time_high.serialized_length()
+    }
+}
+
+impl FromBytes for CollectionStats {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (total_volume, remainder) = U256::from_bytes(bytes)?;
+        let (trade_count, remainder) = u64::from_bytes(remainder)?;
+        let (last_sale_price, remainder) = U256::from_bytes(remainder)?;
+        let (all_time_high, remainder) = U256::from_bytes(remainder)?;
+        Ok((
+            CollectionStats {
+                total_volume,
+                trade_count,
+                last_sale_price,
+                all_time_high,
+            },
+            remainder,
+        ))
+    }
+}
+
+/// Creates the dictionaries backing the statistics, if they don't exist yet.
+pub(crate) fn create_stats_dictionaries() {
+    for dictionary_name in [COLLECTION_STATS, TOKEN_LAST_SALE].iter() {
+        if runtime::get_key(dictionary_name).is_none() {
+            storage::new_dictionary(dictionary_name)
+                .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+        }
+    }
+}
+
+/// Statistics of a collection, all zero before its first trade.
+pub(crate) fn get_collection_stats(nft_contract_hash: &Key) -> CollectionStats {
+    let stats_key = make_dictionary_item_key_for_key(*nft_contract_hash);
+    get_dictionary_value_from_key::<CollectionStats>(COLLECTION_STATS, &stats_key)
+        .unwrap_or_default()
+}
+
+/// Last price a token sold for, None if it never traded on the market.
+pub(crate) fn get_token_last_sale(token_market_key: &str) -> Option<U256> {
+    get_dictionary_value_from_key::<U256>(TOKEN_LAST_SALE, token_market_key)
+}
+
+pub(crate) fn record_sale(nft_contract_hash: &Key, token_market_key: &str, price: U256) {
+    let mut stats = get_collection_stats(nft_contract_hash);
+    stats.total_volume = stats.total_volume + price;
+    stats.trade_count = stats.trade_count + 1;
+    stats.last_sale_price = price;
+    if price > stats.all_time_high {
+        stats.all_time_high = price;
+    }
+
+    let stats_key = make_dictionary_item_key_for_key(*nft_contract_hash);
+    write_dictionary_value_from_key(COLLECTION_STATS, &stats_key, stats);
+    write_dictionary_value_from_key(TOKEN_LAST_SALE, token_market_key, price);
+}
@@ -0,0 +1,136 @@
+//! Minimal CEP-78 collection with ordinal token ids for the market tests. It implements the calls
+//! the market makes, `owner_of` and `transfer`, and a public `mint`. Transfers aren't checked
+//! against approvals.
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+};
+
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{
+    contracts::NamedKeys, ApiError, CLType, CLTyped, CLValue, EntryPoint, EntryPointAccess,
+    EntryPointType, EntryPoints, Key, Parameter, URef,
+};
+
+const MINT_ENTRY_POINT_NAME: &str = "mint";
+const OWNER_OF_ENTRY_POINT_NAME: &str = "owner_of";
+const TRANSFER_ENTRY_POINT_NAME: &str = "transfer";
+const ARG_TOKEN_OWNER: &str = "token_owner";
+const ARG_TOKEN_ID: &str = "token_id";
+const ARG_SOURCE_KEY: &str = "source_key";
+const ARG_TARGET_KEY: &str = "target_key";
+const TOKEN_OWNERS: &str = "token_owners";
+const RECEIPT_NAME: &str = "nft_test";
+const NFT_TEST_CONTRACT_KEY: &str = "nft_test_contract";
+const NFT_TEST_PACKAGE_KEY: &str = "nft_test_contract_package";
+
+#[repr(u16)]
+enum Error {
+    TokenAlreadyMinted = 1,
+    MissingToken = 2,
+    InvalidTokenOwner = 3,
+}
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        ApiError::User(error as u16)
+    }
+}
+
+fn token_owners() -> URef {
+    runtime::get_key(TOKEN_OWNERS)
+        .and_then(|key| key.into_uref())
+        .unwrap_or_revert()
+}
+
+fn get_token_owner(token_id: u64) -> Key {
+    storage::dictionary_get(token_owners(), &token_id.to_string())
+        .unwrap_or_revert()
+        .unwrap_or_revert_with(Error::MissingToken)
+}
+
+#[no_mangle]
+pub extern "C" fn mint() {
+    let token_owner: Key = runtime::get_named_arg(ARG_TOKEN_OWNER);
+    let token_id: u64 = runtime::get_named_arg(ARG_TOKEN_ID);
+    let token_owners = token_owners();
+    let existing_owner: Option<Key> =
+        storage::dictionary_get(token_owners, &token_id.to_string()).unwrap_or_revert();
+    if existing_owner.is_some() {
+        runtime::revert(Error::TokenAlreadyMinted);
+    }
+    storage::dictionary_put(token_owners, &token_id.to_string(), token_owner);
+}
+
+#[no_mangle]
+pub extern "C" fn owner_of() {
+    let token_id: u64 = runtime::get_named_arg(ARG_TOKEN_ID);
+    runtime::ret(CLValue::from_t(get_token_owner(token_id)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn transfer() {
+    let source_key: Key = runtime::get_named_arg(ARG_SOURCE_KEY);
+    let target_key: Key = runtime::get_named_arg(ARG_TARGET_KEY);
+    let token_id: u64 = runtime::get_named_arg(ARG_TOKEN_ID);
+    if get_token_owner(token_id) != source_key {
+        runtime::revert(Error::InvalidTokenOwner);
+    }
+    storage::dictionary_put(token_owners(), &token_id.to_string(), target_key);
+    runtime::ret(CLValue::from_t((String::from(RECEIPT_NAME), target_key)).unwrap_or_revert())
+}
+
+#[no_mangle]
+pub extern "C" fn call() {
+    let mut entry_points = EntryPoints::new();
+    entry_points.add_entry_point(EntryPoint::new(
+        MINT_ENTRY_POINT_NAME,
+        vec![
+            Parameter::new(ARG_TOKEN_OWNER, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::U64),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    ));
+    entry_points.add_entry_point(EntryPoint::new(
+        OWNER_OF_ENTRY_POINT_NAME,
+        vec![Parameter::new(ARG_TOKEN_ID, CLType::U64)],
+        CLType::Key,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    ));
+    entry_points.add_entry_point(EntryPoint::new(
+        TRANSFER_ENTRY_POINT_NAME,
+        vec![
+            Parameter::new(ARG_SOURCE_KEY, CLType::Key),
+            Parameter::new(ARG_TARGET_KEY, CLType::Key),
+            Parameter::new(ARG_TOKEN_ID, CLType::U64),
+        ],
+        <(String, Key)>::cl_type(),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    ));
+
+    // The dictionary is created by the session and handed over to the contract
+    let token_owners = storage::new_dictionary(TOKEN_OWNERS).unwrap_or_revert();
+    runtime::remove_key(TOKEN_OWNERS);
+    let mut named_keys = NamedKeys::new();
+    named_keys.insert(TOKEN_OWNERS.to_string(), token_owners.into());
+
+    let (contract_hash, _version) = storage::new_contract(
+        entry_points,
+        Some(named_keys),
+        Some(NFT_TEST_PACKAGE_KEY.to_string()),
+        None,
+    );
+    runtime::put_key(NFT_TEST_CONTRACT_KEY, contract_hash.into());
+}
@@ -0,0 +1,550 @@
+use blake2::{
+    digest::{Update, VariableOutput},
+    VarBlake2b,
+};
+use once_cell::sync::Lazy;
+
+use casper_engine_test_support::{
+    ExecuteRequestBuilder, InMemoryWasmTestBuilder, DEFAULT_ACCOUNT_ADDR,
+    DEFAULT_RUN_GENESIS_REQUEST, MINIMUM_ACCOUNT_CREATION_BALANCE,
+};
+use casper_execution_engine::core::{
+    engine_state::{Error as CoreError, ExecuteRequest},
+    execution::Error as ExecError,
+};
+use casper_types::{
+    account::AccountHash, bytesrepr::FromBytes, runtime_args, system::mint, ApiError, CLTyped,
+    ContractHash, ContractPackageHash, Key, PublicKey, RuntimeArgs, SecretKey, U256,
+};
+
+const MARKET_CONTRACT_WASM: &str = "contract.wasm";
+const NFT_TEST_WASM: &str = "nft_test.wasm";
+const EXAMPLE_ERC20_TOKEN: &str = "erc20_token.wasm";
+const CONTRACT_ERC20_TEST_CALL: &str = "erc20_test_call.wasm";
+const ERC20_TOKEN_CONTRACT_KEY: &str = "erc20_token_contract";
+const ERC20_TEST_CALL_KEY: &str = "erc20_test_call";
+const NFT_TEST_CONTRACT_KEY: &str = "nft_test_contract";
+const RESULT_KEY: &str = "result";
+
+const MARKET_NAME: &str = "cafi_market";
+const MARKET_PACKAGE_KEY: &str = "cafi_market_package_name";
+
+const ARG_NAME: &str = "name";
+const ARG_SYMBOL: &str = "symbol";
+const ARG_DECIMALS: &str = "decimals";
+const ARG_TOTAL_SUPPLY: &str = "total_supply";
+const ARG_AMOUNT: &str = "amount";
+const ARG_RECIPIENT: &str = "recipient";
+const ARG_SPENDER: &str = "spender";
+const ARG_TOKEN_CONTRACT: &str = "token_contract";
+const ARG_ADDRESS: &str = "address";
+const ARG_TOKEN_OWNER: &str = "token_owner";
+
+const ARG_MARKET_CONTRACT_NAME: &str = "maket_name";
+const ARG_CONTRACT_OWNER: &str = "contract_owner";
+const ARG_MARKET_FEE_RECEIVER: &str = "market_fee_receiver";
+const ARG_WCSPR_CONTRACT: &str = "wcspr_contract";
+const ARG_BUYER_FEE: &str = "buyer_fee";
+const ARG_SELLER_FEE: &str = "seller_fee";
+const ARG_ROYALTY_FEE: &str = "royalty_fee";
+const ARG_IS_ROYALTY: &str = "is_royalty";
+const ARG_NFT_CONTRACT_HASH: &str = "nft_contract_hash";
+const ARG_NFT_ENABLED: &str = "nft_enabled";
+const ARG_IDENTIFIER_MODE: &str = "identifier_mode";
+const ARG_COLLECTION_NAME: &str = "collection_name";
+const ARG_CREATOR: &str = "creator";
+const ARG_TOKEN_ID: &str = "token_id";
+const ARG_MINIMUM_OFFER: &str = "minimum_offer";
+const ARG_BIDDING_OFFER: &str = "bidding_offer";
+const ARG_NEW_OFFER: &str = "new_offer";
+const ARG_REFERRER: &str = "referrer";
+const ARG_REFERRAL_SHARE: &str = "referral_share";
+const ARG_CURRENCY: &str = "currency";
+const ARG_TOKEN_MARKET_KEYS: &str = "token_market_keys";
+
+const METHOD_TRANSFER: &str = "transfer";
+const METHOD_APPROVE: &str = "approve";
+const METHOD_MINT: &str = "mint";
+const CHECK_BALANCE_OF_ENTRYPOINT: &str = "check_balance_of";
+const METHOD_SET_SUPPORT_TOKEN: &str = "set_support_token";
+const METHOD_SET_REFERRAL_SHARE: &str = "set_referral_share";
+const METHOD_OFFER: &str = "offer";
+const METHOD_BID: &str = "bid";
+const METHOD_INCREASE_BID: &str = "increase_bid";
+const METHOD_WITHDRAW: &str = "withdraw";
+const METHOD_REVOKE_BID: &str = "revoke_bid";
+const METHOD_MIGRATE_FEE_MODEL: &str = "migrate_fee_model";
+
+const ERROR_INVALID_CONTRACT_OWNER: u16 = 8;
+const ERROR_INVALID_REFERRER: u16 = 130;
+
+const TOKEN_TOTAL_SUPPLY: u64 = 1_000_000_000;
+const BUYER_BALANCE: u64 = 1_000_000;
+// Fees are in basis points
+const BUYER_FEE: u64 = 100;
+const SELLER_FEE: u64 = 100;
+const REFERRAL_SHARE: u64 = 5_000;
+const PRICE: u64 = 10_000;
+const TOKEN_ID: u64 = 0;
+
+static ACCOUNT_1_SECRET_KEY: Lazy<SecretKey> =
+    Lazy::new(|| SecretKey::secp256k1_from_bytes(&[221u8; 32]).unwrap());
+static ACCOUNT_1_PUBLIC_KEY: Lazy<PublicKey> =
+    Lazy::new(|| PublicKey::from(&*ACCOUNT_1_SECRET_KEY));
+static ACCOUNT_1_ADDR: Lazy<AccountHash> = Lazy::new(|| ACCOUNT_1_PUBLIC_KEY.to_account_hash());
+
+static ACCOUNT_2_SECRET_KEY: Lazy<SecretKey> =
+    Lazy::new(|| SecretKey::secp256k1_from_bytes(&[212u8; 32]).unwrap());
+static ACCOUNT_2_PUBLIC_KEY: Lazy<PublicKey> =
+    Lazy::new(|| PublicKey::from(&*ACCOUNT_2_SECRET_KEY));
+static ACCOUNT_2_ADDR: Lazy<AccountHash> = Lazy::new(|| ACCOUNT_2_PUBLIC_KEY.to_account_hash());
+
+static ACCOUNT_3_SECRET_KEY: Lazy<SecretKey> =
+    Lazy::new(|| SecretKey::secp256k1_from_bytes(&[233u8; 32]).unwrap());
+static ACCOUNT_3_PUBLIC_KEY: Lazy<PublicKey> =
+    Lazy::new(|| PublicKey::from(&*ACCOUNT_3_SECRET_KEY));
+static ACCOUNT_3_ADDR: Lazy<AccountHash> = Lazy::new(|| ACCOUNT_3_PUBLIC_KEY.to_account_hash());
+
+// The seller is account 1, the buyer account 2 and the referrer account 3. The default account
+// owns the market and receives its fees.
+#[derive(Copy, Clone)]
+struct TestContext {
+    market: ContractHash,
+    market_package: ContractPackageHash,
+    nft: ContractHash,
+    wcspr: ContractHash,
+    erc20_test_call: ContractPackageHash,
+}
+
+fn named_key_hash(builder: &InMemoryWasmTestBuilder, name: &str) -> [u8; 32] {
+    let account = builder
+        .get_account(*DEFAULT_ACCOUNT_ADDR)
+        .expect("should have account");
+    account
+        .named_keys()
+        .get(name)
+        .and_then(|key| key.into_hash())
+        .expect("should have hash")
+}
+
+fn setup() -> (InMemoryWasmTestBuilder, TestContext) {
+    let mut builder = InMemoryWasmTestBuilder::default();
+    builder.run_genesis(&*DEFAULT_RUN_GENESIS_REQUEST);
+
+    let id: Option<u64> = None;
+    for account in [*ACCOUNT_1_ADDR, *ACCOUNT_2_ADDR, *ACCOUNT_3_ADDR] {
+        let transfer_args = runtime_args! {
+            mint::ARG_TARGET => account,
+            mint::ARG_AMOUNT => MINIMUM_ACCOUNT_CREATION_BALANCE,
+            mint::ARG_ID => id,
+        };
+        let transfer_request =
+            ExecuteRequestBuilder::transfer(*DEFAULT_ACCOUNT_ADDR, transfer_args).build();
+        builder.exec(transfer_request).expect_success().commit();
+    }
+
+    let install_requests = [
+        ExecuteRequestBuilder::standard(
+            *DEFAULT_ACCOUNT_ADDR,
+            EXAMPLE_ERC20_TOKEN,
+            runtime_args! {
+                ARG_NAME => "Wrapped CSPR",
+                ARG_SYMBOL => "WCSPR",
+                ARG_DECIMALS => 9u8,
+                ARG_TOTAL_SUPPLY => U256::from(TOKEN_TOTAL_SUPPLY),
+            },
+        )
+        .build(),
+        ExecuteRequestBuilder::standard(
+            *DEFAULT_ACCOUNT_ADDR,
+            CONTRACT_ERC20_TEST_CALL,
+            RuntimeArgs::default(),
+        )
+        .build(),
+        ExecuteRequestBuilder::standard(
+            *DEFAULT_ACCOUNT_ADDR,
+            NFT_TEST_WASM,
+            RuntimeArgs::default(),
+        )
+        .build(),
+    ];
+    for install_request in install_requests {
+        builder.exec(install_request).expect_success().commit();
+    }
+    let wcspr = ContractHash::new(named_key_hash(&builder, ERC20_TOKEN_CONTRACT_KEY));
+
+    let install_market_request = ExecuteRequestBuilder::standard(
+        *DEFAULT_ACCOUNT_ADDR,
+        MARKET_CONTRACT_WASM,
+        runtime_args! {
+            ARG_MARKET_CONTRACT_NAME => MARKET_NAME,
+            ARG_CONTRACT_OWNER => Key::Account(*DEFAULT_ACCOUNT_ADDR),
+            ARG_MARKET_FEE_RECEIVER => Key::Account(*DEFAULT_ACCOUNT_ADDR),
+            ARG_WCSPR_CONTRACT => Key::from(wcspr),
+            ARG_BUYER_FEE => U256::from(BUYER_FEE),
+            ARG_SELLER_FEE => U256::from(SELLER_FEE),
+            ARG_ROYALTY_FEE => U256::zero(),
+            ARG_IS_ROYALTY => false,
+        },
+    )
+    .build();
+    builder
+        .exec(install_market_request)
+        .expect_success()
+        .commit();
+
+    let test_context = TestContext {
+        market: ContractHash::new(named_key_hash(&builder, MARKET_NAME)),
+        market_package: ContractPackageHash::new(named_key_hash(&builder, MARKET_PACKAGE_KEY)),
+        nft: ContractHash::new(named_key_hash(&builder, NFT_TEST_CONTRACT_KEY)),
+        wcspr,
+        erc20_test_call: ContractPackageHash::new(named_key_hash(&builder, ERC20_TEST_CALL_KEY)),
+    };
+
+    builder
+        .exec(call_market(
+            &test_context,
+            *DEFAULT_ACCOUNT_ADDR,
+            METHOD_SET_SUPPORT_TOKEN,
+            runtime_args! {
+                ARG_NFT_CONTRACT_HASH => Key::from(test_context.nft),
+                ARG_NFT_ENABLED => true,
+                ARG_IDENTIFIER_MODE => 0u8,
+                ARG_COLLECTION_NAME => "Test collection",
+                ARG_CREATOR => Key::Account(*DEFAULT_ACCOUNT_ADDR),
+            },
+        ))
+        .expect_success()
+        .commit();
+    builder
+        .exec(call_market(
+            &test_context,
+            *DEFAULT_ACCOUNT_ADDR,
+            METHOD_SET_REFERRAL_SHARE,
+            runtime_args! {
+                ARG_REFERRAL_SHARE => U256::from(REFERRAL_SHARE),
+            },
+        ))
+        .expect_success()
+        .commit();
+
+    let mint_request = ExecuteRequestBuilder::contract_call_by_hash(
+        *DEFAULT_ACCOUNT_ADDR,
+        test_context.nft,
+        METHOD_MINT,
+        runtime_args! {
+            ARG_TOKEN_OWNER => Key::Account(*ACCOUNT_1_ADDR),
+            ARG_TOKEN_ID => TOKEN_ID,
+        },
+    )
+    .build();
+    builder.exec(mint_request).expect_success().commit();
+    builder
+        .exec(call_market(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_OFFER,
+            runtime_args! {
+                ARG_NFT_CONTRACT_HASH => Key::from(test_context.nft),
+                ARG_TOKEN_ID => TOKEN_ID,
+                ARG_MINIMUM_OFFER => U256::from(PRICE),
+            },
+        ))
+        .expect_success()
+        .commit();
+
+    let transfer_request = ExecuteRequestBuilder::contract_call_by_hash(
+        *DEFAULT_ACCOUNT_ADDR,
+        wcspr,
+        METHOD_TRANSFER,
+        runtime_args! {
+            ARG_RECIPIENT => Key::Account(*ACCOUNT_2_ADDR),
+            ARG_AMOUNT => U256::from(BUYER_BALANCE),
+        },
+    )
+    .build();
+    builder.exec(transfer_request).expect_success().commit();
+    // The market moves tokens as its package
+    let approve_request = ExecuteRequestBuilder::contract_call_by_hash(
+        *ACCOUNT_2_ADDR,
+        wcspr,
+        METHOD_APPROVE,
+        runtime_args! {
+            ARG_SPENDER => Key::Hash(test_context.market_package.value()),
+            ARG_AMOUNT => U256::from(BUYER_BALANCE),
+        },
+    )
+    .build();
+    builder.exec(approve_request).expect_success().commit();
+
+    (builder, test_context)
+}
+
+fn call_market(
+    test_context: &TestContext,
+    sender: AccountHash,
+    entry_point: &str,
+    args: RuntimeArgs,
+) -> ExecuteRequest {
+    ExecuteRequestBuilder::contract_call_by_hash(sender, test_context.market, entry_point, args)
+        .build()
+}
+
+fn bid(test_context: &TestContext, bidding_offer: u64, referrer: Key) -> ExecuteRequest {
+    call_market(
+        test_context,
+        *ACCOUNT_2_ADDR,
+        METHOD_BID,
+        runtime_args! {
+            ARG_NFT_CONTRACT_HASH => Key::from(test_context.nft),
+            ARG_TOKEN_ID => TOKEN_ID,
+            ARG_BIDDING_OFFER => U256::from(bidding_offer),
+            ARG_REFERRER => referrer,
+        },
+    )
+}
+
+// Withdraws the claimable balance of an account and returns the amount it received
+fn withdraw(
+    builder: &mut InMemoryWasmTestBuilder,
+    test_context: &TestContext,
+    account: AccountHash,
+) -> U256 {
+    let balance_before = erc20_check_balance_of(builder, test_context, Key::Account(account));
+    builder
+        .exec(call_market(
+            test_context,
+            account,
+            METHOD_WITHDRAW,
+            runtime_args! {
+                ARG_CURRENCY => Key::from(test_context.wcspr),
+            },
+        ))
+        .expect_success()
+        .commit();
+    erc20_check_balance_of(builder, test_context, Key::Account(account)) - balance_before
+}
+
+fn erc20_check_balance_of(
+    builder: &mut InMemoryWasmTestBuilder,
+    test_context: &TestContext,
+    address: Key,
+) -> U256 {
+    let check_balance_args = runtime_args! {
+        ARG_TOKEN_CONTRACT => test_context.wcspr,
+        ARG_ADDRESS => address,
+    };
+    let exec_request = ExecuteRequestBuilder::versioned_contract_call_by_hash(
+        *DEFAULT_ACCOUNT_ADDR,
+        test_context.erc20_test_call,
+        None,
+        CHECK_BALANCE_OF_ENTRYPOINT,
+        check_balance_args,
+    )
+    .build();
+    builder.exec(exec_request).expect_success().commit();
+
+    let contract_package = builder
+        .get_contract_package(test_context.erc20_test_call)
+        .expect("should have contract package");
+    let (_version, contract_hash) = contract_package
+        .enabled_versions()
+        .iter()
+        .rev()
+        .next()
+        .expect("should have latest version");
+    builder
+        .query(None, Key::from(*contract_hash), &[RESULT_KEY.to_string()])
+        .expect("should have result")
+        .as_cl_value()
+        .expect("should be CLValue")
+        .clone()
+        .into_t()
+        .expect("should be U256")
+}
+
+fn query_market<T: CLTyped + FromBytes>(
+    builder: &InMemoryWasmTestBuilder,
+    test_context: &TestContext,
+    name: &str,
+) -> T {
+    builder
+        .query(None, Key::from(test_context.market), &[name.to_string()])
+        .expect("should have named key")
+        .as_cl_value()
+        .expect("should be CLValue")
+        .clone()
+        .into_t()
+        .expect("should have the expected type")
+}
+
+// Same key the market derives for its TOKEN_MARKET dictionary
+fn token_market_key(test_context: &TestContext, token_id: u64) -> String {
+    let mut hasher = VarBlake2b::new(32).unwrap();
+    hasher.update(Key::from(test_context.nft).to_string().as_bytes());
+    hasher.update(token_id.to_string().as_bytes());
+    let mut key_bytes = Vec::new();
+    hasher.finalize_variable(|hash| key_bytes.extend_from_slice(hash));
+    hex::encode(key_bytes)
+}
+
+fn assert_user_error(builder: &InMemoryWasmTestBuilder, expected_error: u16) {
+    let error = builder.get_error().expect("should have error");
+    assert!(
+        matches!(error, CoreError::Exec(ExecError::Revert(ApiError::User(user_error))) if user_error == expected_error),
+        "{:?}",
+        error
+    );
+}
+
+// Checks how a sale at PRICE with a referrer was paid out
+fn assert_referred_sale(builder: &mut InMemoryWasmTestBuilder, test_context: &TestContext) {
+    let buyer_fee = PRICE * BUYER_FEE / 10_000;
+    let seller_fee = PRICE * SELLER_FEE / 10_000;
+    let referral_amount = (buyer_fee + seller_fee) * REFERRAL_SHARE / 10_000;
+
+    let buyer_balance =
+        erc20_check_balance_of(builder, test_context, Key::Account(*ACCOUNT_2_ADDR));
+    assert_eq!(buyer_balance, U256::from(BUYER_BALANCE - PRICE - buyer_fee));
+    assert_eq!(
+        withdraw(builder, test_context, *ACCOUNT_3_ADDR),
+        U256::from(referral_amount)
+    );
+    assert_eq!(
+        withdraw(builder, test_context, *DEFAULT_ACCOUNT_ADDR),
+        U256::from(buyer_fee + seller_fee - referral_amount)
+    );
+    assert_eq!(
+        withdraw(builder, test_context, *ACCOUNT_1_ADDR),
+        U256::from(PRICE - seller_fee)
+    );
+
+    // The token went to the buyer, only its owner can list it
+    builder
+        .exec(call_market(
+            test_context,
+            *ACCOUNT_2_ADDR,
+            METHOD_OFFER,
+            runtime_args! {
+                ARG_NFT_CONTRACT_HASH => Key::from(test_context.nft),
+                ARG_TOKEN_ID => TOKEN_ID,
+                ARG_MINIMUM_OFFER => U256::from(PRICE),
+            },
+        ))
+        .expect_success()
+        .commit();
+}
+
+#[test]
+fn should_pay_the_referrer_its_share_of_the_market_fee() {
+    let (mut builder, test_context) = setup();
+
+    builder
+        .exec(bid(&test_context, PRICE, Key::Account(*ACCOUNT_3_ADDR)))
+        .expect_success()
+        .commit();
+
+    assert_referred_sale(&mut builder, &test_context);
+}
+
+#[test]
+fn should_keep_the_referrer_of_a_bid_raised_to_the_price() {
+    let (mut builder, test_context) = setup();
+
+    builder
+        .exec(bid(&test_context, PRICE / 2, Key::Account(*ACCOUNT_3_ADDR)))
+        .expect_success()
+        .commit();
+    // Raised without a referrer, the trade settles with the one the bid was placed with
+    builder
+        .exec(call_market(
+            &test_context,
+            *ACCOUNT_2_ADDR,
+            METHOD_INCREASE_BID,
+            runtime_args! {
+                ARG_NFT_CONTRACT_HASH => Key::from(test_context.nft),
+                ARG_TOKEN_ID => TOKEN_ID,
+                ARG_NEW_OFFER => U256::from(PRICE),
+            },
+        ))
+        .expect_success()
+        .commit();
+
+    assert_referred_sale(&mut builder, &test_context);
+}
+
+#[test]
+fn should_not_let_traders_refer_their_own_bid() {
+    let (mut builder, test_context) = setup();
+
+    for referrer in [*ACCOUNT_2_ADDR, *ACCOUNT_1_ADDR] {
+        // Stored with the bid or paid right away, a trader is never its own referrer
+        for bidding_offer in [PRICE / 2, PRICE] {
+            builder
+                .exec(bid(&test_context, bidding_offer, Key::Account(referrer)))
+                .commit();
+
+            assert_user_error(&builder, ERROR_INVALID_REFERRER);
+        }
+    }
+}
+
+#[test]
+fn should_keep_fees_and_escrow_when_the_fee_migration_reruns() {
+    let (mut builder, test_context) = setup();
+
+    builder
+        .exec(bid(&test_context, PRICE / 2, Key::Account(*ACCOUNT_3_ADDR)))
+        .expect_success()
+        .commit();
+    let migrate_fee_model_args = runtime_args! {
+        ARG_TOKEN_MARKET_KEYS => vec![token_market_key(&test_context, TOKEN_ID)],
+    };
+
+    builder
+        .exec(call_market(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_MIGRATE_FEE_MODEL,
+            migrate_fee_model_args.clone(),
+        ))
+        .commit();
+    assert_user_error(&builder, ERROR_INVALID_CONTRACT_OWNER);
+
+    // The market was installed on the current fee model, migrating it again changes nothing
+    builder
+        .exec(call_market(
+            &test_context,
+            *DEFAULT_ACCOUNT_ADDR,
+            METHOD_MIGRATE_FEE_MODEL,
+            migrate_fee_model_args,
+        ))
+        .expect_success()
+        .commit();
+    for (name, expected_fee) in [
+        (ARG_BUYER_FEE, BUYER_FEE),
+        (ARG_SELLER_FEE, SELLER_FEE),
+        (ARG_ROYALTY_FEE, 0),
+        (ARG_REFERRAL_SHARE, REFERRAL_SHARE),
+    ] {
+        let fee: U256 = query_market(&builder, &test_context, name);
+        assert_eq!(fee, U256::from(expected_fee), "{}", name);
+    }
+
+    // The escrowed bid and its buyer fee come back whole
+    builder
+        .exec(call_market(
+            &test_context,
+            *ACCOUNT_2_ADDR,
+            METHOD_REVOKE_BID,
+            runtime_args! {
+                ARG_NFT_CONTRACT_HASH => Key::from(test_context.nft),
+                ARG_TOKEN_ID => TOKEN_ID,
+            },
+        ))
+        .expect_success()
+        .commit();
+    let buyer_balance =
+        erc20_check_balance_of(&mut builder, &test_context, Key::Account(*ACCOUNT_2_ADDR));
+    assert_eq!(buyer_balance, U256::from(BUYER_BALANCE));
+}
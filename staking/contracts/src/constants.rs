@@ -203,6 +203,7 @@ pub const ADD_NEW_POOL : &str = "add_new_pool";
 pub const STAKE : &str = "stake";
 pub const UN_STAKE : &str = "un_stake";
 pub const GET_PENDING_REWARD : &str = "get_pending_rewards";
+pub const GET_USER_STAKE : &str = "get_user_stake";
 pub const FEE_PORTION : &str = "fee_portion";
 pub const GET_APPROVED_ENTRY_POINT_NAME : &str = "get_approved";
 pub const ARG_BIDDING_OFFER : &str = "bidding_offer";
@@ -225,4 +226,55 @@ pub const ARG_PENALTY_RATE : &str = "penalty_rate";
 pub const ARG_USER : &str = "user";
 pub const POOL_LIST : &str = "pool_list";
 pub const TOTAL_ALLOC_POINT : &str = "total_alloc_point";
-pub const NUMBER_OF_POOL : &str = "number_of_pool";
\ No newline at end of file
+pub const NUMBER_OF_POOL : &str = "number_of_pool";
+pub const MIGRATE_STORAGE : &str = "migrate_storage";
+pub const ARG_POOL_IDS : &str = "pool_ids";
+pub const ARG_USER_INFO_KEYS : &str = "user_info_keys";
+pub const ARG_LOCK_TIER : &str = "lock_tier";
+pub const ARG_LOCK_DURATIONS : &str = "lock_durations";
+pub const ARG_LOCK_MULTIPLIERS : &str = "lock_multipliers";
+pub const ARG_TREASURY : &str = "treasury";
+pub const LOCK_TIER_DURATIONS : &str = "lock_tier_durations";
+pub const LOCK_TIER_MULTIPLIERS : &str = "lock_tier_multipliers";
+pub const TREASURY : &str = "treasury";
+pub const SET_LOCK_TIERS : &str = "set_lock_tiers";
+pub const SET_TREASURY : &str = "set_treasury";
+pub const EXPIRE_LOCK : &str = "expire_lock";
+pub const GET_USER_LOCK : &str = "get_user_lock";
+pub const EMERGENCY_WITHDRAW : &str = "emergency_withdraw";
+pub const HARVEST : &str = "harvest";
+pub const HARVEST_ALL : &str = "harvest_all";
+pub const COMPOUND : &str = "compound";
+pub const SET_POOL : &str = "set_pool";
+pub const MASS_UPDATE_POOLS : &str = "mass_update_pools";
+pub const ARG_EPOCH_STARTS : &str = "epoch_starts";
+pub const ARG_EPOCH_RATES : &str = "epoch_rates";
+pub const ARG_EMISSION_END : &str = "emission_end";
+pub const EMISSION_EPOCH_STARTS : &str = "emission_epoch_starts";
+pub const EMISSION_EPOCH_RATES : &str = "emission_epoch_rates";
+pub const EMISSION_END : &str = "emission_end";
+pub const SET_EMISSION_SCHEDULE : &str = "set_emission_schedule";
+pub const ARG_DURATION : &str = "duration";
+pub const FUNDED_REWARDS : &str = "funded_rewards";
+pub const NOTIFY_REWARD_AMOUNT : &str = "notify_reward_amount";
+pub const ARG_DISTRIBUTOR : &str = "distributor";
+pub const ARG_STREAM_INDEX : &str = "stream_index";
+pub const ADD_REWARD_STREAM : &str = "add_reward_stream";
+pub const NOTIFY_STREAM_REWARD : &str = "notify_stream_reward";
+pub const GET_PENDING_STREAM_REWARDS : &str = "get_pending_stream_rewards";
+pub const ARG_DEPOSIT_FEE_RATE : &str = "deposit_fee_rate";
+pub const ARG_WITHDRAW_FEE_RATE : &str = "withdraw_fee_rate";
+pub const SET_POOL_FEES : &str = "set_pool_fees";
+pub const ARG_MAX_POOL_STAKE : &str = "max_pool_stake";
+pub const ARG_MAX_USER_STAKE : &str = "max_user_stake";
+pub const ARG_PRIVATE_POOL : &str = "private_pool";
+pub const ARG_USERS : &str = "users";
+pub const ARG_ALLOWED : &str = "allowed";
+pub const POOL_ALLOWLIST : &str = "pool_allowlist";
+pub const SET_POOL_LIMITS : &str = "set_pool_limits";
+pub const SET_POOL_ALLOWLIST : &str = "set_pool_allowlist";
+pub const IS_ALLOWED_STAKER : &str = "is_allowed_staker";
+pub const ARG_BENEFICIARY : &str = "beneficiary";
+pub const STAKE_FOR : &str = "stake_for";
+pub const UNDISTRIBUTED_REWARDS : &str = "undistributed_rewards";
+pub const GET_LOCKED_STAKES : &str = "get_locked_stakes";
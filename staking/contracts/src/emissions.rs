@@ -0,0 +1,104 @@
+//! Reward emission schedule of the staking pools.
+//!
+//! The owner sets epochs with `set_emission_schedule`: epoch n emits `rates[n]` per unit of block
+//! time from `starts[n]` until the next epoch starts, the last epoch runs until `end` and nothing
+//! is emitted after it. Without a schedule the contract emits `REWARD_PER_SECOND` without end.
+//!
+//! Rewards are minted by default. Once the owner deposits a budget with `notify_reward_amount`
+//! the contract switches to funded rewards for good: the schedule becomes a single epoch that
+//! spends the budget over the given duration and nothing is minted anymore. What a pool's share
+//! of the budget would have paid while nothing was staked in it is kept aside and added to the
+//! next budget.
+use alloc::{vec, vec::Vec};
+use casper_contract::contract_api::runtime;
+use casper_types::U256;
+use core::cmp::{max, min};
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{get_key, get_stored_value_with_user_errors, set_key};
+
+pub(crate) fn validate_emission_schedule(starts: &[U256], rates: &[U256], end: U256) {
+    if starts.len() != rates.len() {
+        runtime::revert(Error::InvalidEmissionSchedule);
+    }
+    if starts.windows(2).any(|pair| pair[0] >= pair[1]) {
+        runtime::revert(Error::InvalidEmissionSchedule);
+    }
+    if let Some(last_start) = starts.last() {
+        if end <= *last_start {
+            runtime::revert(Error::InvalidEmissionSchedule);
+        }
+    }
+}
+
+/// Rewards emitted over `[from, to]`, to be split between the pools by alloc point.
+pub(crate) fn get_emission(from: U256, to: U256) -> U256 {
+    let starts: Vec<U256> = get_key(EMISSION_EPOCH_STARTS).unwrap_or_default();
+    if starts.is_empty() {
+        let reward_per_second: U256 = get_stored_value_with_user_errors(
+            REWARD_PER_SECOND,
+            Error::MissingRewardPerSecond,
+            Error::InvalidRewardPerSecond,
+        );
+        return (to - from) * reward_per_second;
+    }
+
+    let rates: Vec<U256> = get_key(EMISSION_EPOCH_RATES).unwrap_or_default();
+    let end: U256 = get_key(EMISSION_END).unwrap_or_default();
+    let mut emission = U256::zero();
+    for (index, (start, rate)) in starts.iter().zip(rates.iter()).enumerate() {
+        let epoch_end = starts.get(index + 1).copied().unwrap_or(end);
+        let lower = max(from, *start);
+        let upper = min(to, epoch_end);
+        if upper > lower {
+            emission += (upper - lower) * *rate;
+        }
+    }
+    emission
+}
+
+pub(crate) fn is_funded() -> bool {
+    get_key::<bool>(FUNDED_REWARDS).unwrap_or_default()
+}
+
+/// Schedule changes would emit more than the deposited budget.
+pub(crate) fn require_minted_rewards() {
+    if is_funded() {
+        runtime::revert(Error::RewardsAreFunded);
+    }
+}
+
+/// Keeps funded rewards no staker earned for the next budget.
+pub(crate) fn add_undistributed_rewards(amount: U256) {
+    let undistributed: U256 = get_key(UNDISTRIBUTED_REWARDS).unwrap_or_default();
+    set_key(UNDISTRIBUTED_REWARDS, undistributed + amount);
+}
+
+/// Spreads `amount`, plus what the running funded period hasn't emitted yet and the rewards no
+/// staker earned, evenly over `duration` from `now`. Returns the new rate.
+pub(crate) fn start_funded_period(now: U256, amount: U256, duration: U256) -> U256 {
+    if duration == U256::zero() {
+        runtime::revert(Error::InvalidRewardDuration);
+    }
+
+    let mut budget = amount;
+    if is_funded() {
+        let end: U256 = get_key(EMISSION_END).unwrap_or_default();
+        if now < end {
+            budget += get_emission(now, end);
+        }
+        budget += get_key::<U256>(UNDISTRIBUTED_REWARDS).unwrap_or_default();
+    }
+    let rate = budget / duration;
+    if rate == U256::zero() {
+        runtime::revert(Error::InvalidAmount);
+    }
+
+    set_key(EMISSION_EPOCH_STARTS, vec![now]);
+    set_key(EMISSION_EPOCH_RATES, vec![rate]);
+    set_key(EMISSION_END, now + duration);
+    set_key(FUNDED_REWARDS, true);
+    set_key(UNDISTRIBUTED_REWARDS, U256::zero());
+    rate
+}
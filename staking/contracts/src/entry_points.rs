@@ -85,6 +85,299 @@ fn get_pending_rewards() -> EntryPoint {
     )
 }
 
+fn get_user_stake() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_USER_STAKE),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_USER, CLType::Key),
+        ],
+        CLType::U256,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_user_lock() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_USER_LOCK),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_USER, CLType::Key),
+        ],
+        CLType::Tuple3([
+            Box::new(CLType::U8),
+            Box::new(CLType::U256),
+            Box::new(CLType::U256),
+        ]),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_locked_stakes() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_LOCKED_STAKES),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_USERS, CLType::List(Box::new(CLType::Key))),
+        ],
+        CLType::List(Box::new(CLType::U256)),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn expire_lock() -> EntryPoint {
+    EntryPoint::new(
+        String::from(EXPIRE_LOCK),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_USER, CLType::Key),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_lock_tiers() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_LOCK_TIERS),
+        vec![
+            Parameter::new(ARG_LOCK_DURATIONS, CLType::List(Box::new(CLType::U256))),
+            Parameter::new(ARG_LOCK_MULTIPLIERS, CLType::List(Box::new(CLType::U256))),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_treasury() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_TREASURY),
+        vec![Parameter::new(ARG_TREASURY, CLType::Key)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn emergency_withdraw() -> EntryPoint {
+    EntryPoint::new(
+        String::from(EMERGENCY_WITHDRAW),
+        vec![Parameter::new(ARG_POOL_ID, CLType::U64)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn harvest() -> EntryPoint {
+    EntryPoint::new(
+        String::from(HARVEST),
+        vec![Parameter::new(ARG_POOL_ID, CLType::U64)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn harvest_all() -> EntryPoint {
+    EntryPoint::new(
+        String::from(HARVEST_ALL),
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn compound() -> EntryPoint {
+    EntryPoint::new(
+        String::from(COMPOUND),
+        vec![Parameter::new(ARG_POOL_ID, CLType::U64)],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_pool() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_POOL),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_ALLOC_POINT, CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn mass_update_pools() -> EntryPoint {
+    EntryPoint::new(
+        String::from(MASS_UPDATE_POOLS),
+        vec![],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_emission_schedule() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_EMISSION_SCHEDULE),
+        vec![
+            Parameter::new(ARG_EPOCH_STARTS, CLType::List(Box::new(CLType::U256))),
+            Parameter::new(ARG_EPOCH_RATES, CLType::List(Box::new(CLType::U256))),
+            Parameter::new(ARG_EMISSION_END, CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn notify_reward_amount() -> EntryPoint {
+    EntryPoint::new(
+        String::from(NOTIFY_REWARD_AMOUNT),
+        vec![
+            Parameter::new(ARG_AMOUNT, CLType::U256),
+            Parameter::new(ARG_DURATION, CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn add_reward_stream() -> EntryPoint {
+    EntryPoint::new(
+        String::from(ADD_REWARD_STREAM),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_REWARD_TOKEN, CLType::Key),
+            Parameter::new(ARG_DISTRIBUTOR, CLType::Key),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn notify_stream_reward() -> EntryPoint {
+    EntryPoint::new(
+        String::from(NOTIFY_STREAM_REWARD),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_STREAM_INDEX, CLType::U32),
+            Parameter::new(ARG_AMOUNT, CLType::U256),
+            Parameter::new(ARG_DURATION, CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn get_pending_stream_rewards() -> EntryPoint {
+    EntryPoint::new(
+        String::from(GET_PENDING_STREAM_REWARDS),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_USER, CLType::Key),
+        ],
+        CLType::List(Box::new(CLType::U256)),
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_pool_fees() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_POOL_FEES),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_DEPOSIT_FEE_RATE, CLType::U256),
+            Parameter::new(ARG_WITHDRAW_FEE_RATE, CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_pool_limits() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_POOL_LIMITS),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_MAX_POOL_STAKE, CLType::U256),
+            Parameter::new(ARG_MAX_USER_STAKE, CLType::U256),
+            Parameter::new(ARG_PRIVATE_POOL, CLType::Bool),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn set_pool_allowlist() -> EntryPoint {
+    EntryPoint::new(
+        String::from(SET_POOL_ALLOWLIST),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_USERS, CLType::List(Box::new(CLType::Key))),
+            Parameter::new(ARG_ALLOWED, CLType::Bool),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn is_allowed_staker() -> EntryPoint {
+    EntryPoint::new(
+        String::from(IS_ALLOWED_STAKER),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_USER, CLType::Key),
+        ],
+        CLType::Bool,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn stake_for() -> EntryPoint {
+    EntryPoint::new(
+        String::from(STAKE_FOR),
+        vec![
+            Parameter::new(ARG_POOL_ID, CLType::U64),
+            Parameter::new(ARG_BENEFICIARY, CLType::Key),
+            Parameter::new(ARG_AMOUNT, CLType::U256),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
+
+fn migrate_storage() -> EntryPoint {
+    EntryPoint::new(
+        String::from(MIGRATE_STORAGE),
+        vec![
+            Parameter::new(ARG_POOL_IDS, CLType::List(Box::new(CLType::U64))),
+            Parameter::new(ARG_USER_INFO_KEYS, CLType::List(Box::new(CLType::String))),
+        ],
+        CLType::Unit,
+        EntryPointAccess::Public,
+        EntryPointType::Contract,
+    )
+}
 
 fn init() -> EntryPoint {
     EntryPoint::new(
@@ -113,5 +406,28 @@ pub(crate) fn default() -> EntryPoints {
     entry_points.add_entry_point(stake());
     entry_points.add_entry_point(un_stake());
     entry_points.add_entry_point(get_pending_rewards());
+    entry_points.add_entry_point(get_user_stake());
+    entry_points.add_entry_point(migrate_storage());
+    entry_points.add_entry_point(get_user_lock());
+    entry_points.add_entry_point(get_locked_stakes());
+    entry_points.add_entry_point(expire_lock());
+    entry_points.add_entry_point(set_lock_tiers());
+    entry_points.add_entry_point(set_treasury());
+    entry_points.add_entry_point(emergency_withdraw());
+    entry_points.add_entry_point(harvest());
+    entry_points.add_entry_point(harvest_all());
+    entry_points.add_entry_point(compound());
+    entry_points.add_entry_point(set_pool());
+    entry_points.add_entry_point(mass_update_pools());
+    entry_points.add_entry_point(set_emission_schedule());
+    entry_points.add_entry_point(notify_reward_amount());
+    entry_points.add_entry_point(add_reward_stream());
+    entry_points.add_entry_point(notify_stream_reward());
+    entry_points.add_entry_point(get_pending_stream_rewards());
+    entry_points.add_entry_point(set_pool_fees());
+    entry_points.add_entry_point(set_pool_limits());
+    entry_points.add_entry_point(set_pool_allowlist());
+    entry_points.add_entry_point(is_allowed_staker());
+    entry_points.add_entry_point(stake_for());
     entry_points
 }
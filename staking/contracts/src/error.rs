@@ -52,8 +52,27 @@ pub enum Error {
     InvalidNumberOfPool = 126,
     CanNotGetUserInfo = 127,
     FailedToConvertToCLValue = 128,
-
-    
+    StakeLocked = 129,
+    InvalidLockTier = 130,
+    InvalidLockTiers = 131,
+    InvalidPenaltyRate = 132,
+    LockNotExpired = 133,
+    MissingTreasury = 134,
+    InvalidTreasury = 135,
+    NothingStaked = 136,
+    CannotCompound = 137,
+    InvalidEmissionSchedule = 138,
+    InvalidRewardDuration = 139,
+    RewardsAreFunded = 140,
+    TooManyRewardStreams = 141,
+    RewardStreamExists = 142,
+    InvalidRewardStream = 143,
+    InvalidStreamDistributor = 144,
+    InvalidPoolFeeRate = 145,
+    PoolStakeCapExceeded = 146,
+    UserStakeCapExceeded = 147,
+    StakerNotAllowed = 148,
+    InvalidStreamRewardToken = 149,
 }
 
 impl From<Error> for ApiError {
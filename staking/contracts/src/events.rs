@@ -46,11 +46,62 @@ pub enum StakingEvent {
 
     UserStake {
         user: Key,
+        staked_by: Key,
         lp_token: Key,
         amount: U256,
-        // stake_duration: U256,
+        lock_tier: u8,
+        locked_until: U256,
         // token_id: u8,
     },
+    EarlyWithdrawPenalty {
+        user: Key,
+        pool_id: u64,
+        treasury: Key,
+        penalty: U256,
+    },
+    LockExpired {
+        user: Key,
+        pool_id: u64,
+    },
+    PoolFeeCharged {
+        user: Key,
+        pool_id: u64,
+        treasury: Key,
+        fee: U256,
+    },
+    EmergencyWithdraw {
+        user: Key,
+        pool_id: u64,
+        amount: U256,
+    },
+    Harvest {
+        user: Key,
+        pool_id: u64,
+        amount: U256,
+    },
+    Compound {
+        user: Key,
+        pool_id: u64,
+        amount: U256,
+    },
+    RewardStreamFunded {
+        pool_id: u64,
+        reward_token: Key,
+        amount: U256,
+        reward_per_second: U256,
+        period_finish: U256,
+    },
+    StreamRewardPaid {
+        user: Key,
+        pool_id: u64,
+        reward_token: Key,
+        amount: U256,
+    },
+    RewardFunded {
+        amount: U256,
+        reward_per_second: U256,
+        period_finish: U256,
+    },
     Withdrawal {
         cspr_recipient: AccountHash,
         from: Key,
@@ -92,11 +143,62 @@ impl StakingEvent {
 
             StakingEvent::UserStake {
                 user: _,
+                staked_by: _,
                 lp_token: _,
                 amount: _,
-                // stake_duration: _,
+                lock_tier: _,
+                locked_until: _,
                 // token_id: _,
             } => "user_stake",
+            StakingEvent::EarlyWithdrawPenalty {
+                user: _,
+                pool_id: _,
+                treasury: _,
+                penalty: _,
+            } => "early_withdraw_penalty",
+            StakingEvent::LockExpired {
+                user: _,
+                pool_id: _,
+            } => "lock_expired",
+            StakingEvent::PoolFeeCharged {
+                user: _,
+                pool_id: _,
+                treasury: _,
+                fee: _,
+            } => "pool_fee_charged",
+            StakingEvent::EmergencyWithdraw {
+                user: _,
+                pool_id: _,
+                amount: _,
+            } => "emergency_withdraw",
+            StakingEvent::Harvest {
+                user: _,
+                pool_id: _,
+                amount: _,
+            } => "harvest",
+            StakingEvent::Compound {
+                user: _,
+                pool_id: _,
+                amount: _,
+            } => "compound",
+            StakingEvent::RewardStreamFunded {
+                pool_id: _,
+                reward_token: _,
+                amount: _,
+                reward_per_second: _,
+                period_finish: _,
+            } => "reward_stream_funded",
+            StakingEvent::StreamRewardPaid {
+                user: _,
+                pool_id: _,
+                reward_token: _,
+                amount: _,
+            } => "stream_reward_paid",
+            StakingEvent::RewardFunded {
+                amount: _,
+                reward_per_second: _,
+                period_finish: _,
+            } => "reward_funded",
             StakingEvent::Withdrawal {
                 cspr_recipient: _,
                 from: _,
@@ -177,18 +279,128 @@ pub(crate) fn emit(pair_event: &StakingEvent) {
         }
         StakingEvent::UserStake {
             user,
+            staked_by,
             lp_token,
             amount,
-            // stake_duration,
+            lock_tier,
+            locked_until,
             // token_id,
         } => {
             let mut event = BTreeMap::new();
             event.insert("contract_package_hash", package.to_string());
             event.insert("event_type", pair_event.type_name());
             event.insert("user", user.to_string());
+            event.insert("staked_by", staked_by.to_string());
             event.insert("lp_token", lp_token.to_string());
             event.insert("amount", amount.to_string());
-            // event.insert("stake_duration", stake_duration.to_string());
+            event.insert("lock_tier", lock_tier.to_string());
+            event.insert("locked_until", locked_until.to_string());
+            events.push(event);
+        }
+        StakingEvent::EarlyWithdrawPenalty {
+            user,
+            pool_id,
+            treasury,
+            penalty,
+        } => {
+            let mut event = BTreeMap::new();
+            event.insert("contract_package_hash", package.to_string());
+            event.insert("event_type", pair_event.type_name());
+            event.insert("user", user.to_string());
+            event.insert("pool_id", pool_id.to_string());
+            event.insert("treasury", treasury.to_string());
+            event.insert("penalty", penalty.to_string());
+            events.push(event);
+        }
+        StakingEvent::PoolFeeCharged {
+            user,
+            pool_id,
+            treasury,
+            fee,
+        } => {
+            let mut event = BTreeMap::new();
+            event.insert("contract_package_hash", package.to_string());
+            event.insert("event_type", pair_event.type_name());
+            event.insert("user", user.to_string());
+            event.insert("pool_id", pool_id.to_string());
+            event.insert("treasury", treasury.to_string());
+            event.insert("fee", fee.to_string());
+            events.push(event);
+        }
+        StakingEvent::LockExpired { user, pool_id } => {
+            let mut event = BTreeMap::new();
+            event.insert("contract_package_hash", package.to_string());
+            event.insert("event_type", pair_event.type_name());
+            event.insert("user", user.to_string());
+            event.insert("pool_id", pool_id.to_string());
+            events.push(event);
+        }
+        StakingEvent::EmergencyWithdraw {
+            user,
+            pool_id,
+            amount,
+        }
+        | StakingEvent::Harvest {
+            user,
+            pool_id,
+            amount,
+        }
+        | StakingEvent::Compound {
+            user,
+            pool_id,
+            amount,
+        } => {
+            let mut event = BTreeMap::new();
+            event.insert("contract_package_hash", package.to_string());
+            event.insert("event_type", pair_event.type_name());
+            event.insert("user", user.to_string());
+            event.insert("pool_id", pool_id.to_string());
+            event.insert("amount", amount.to_string());
+            events.push(event);
+        }
+        StakingEvent::RewardStreamFunded {
+            pool_id,
+            reward_token,
+            amount,
+            reward_per_second,
+            period_finish,
+        } => {
+            let mut event = BTreeMap::new();
+            event.insert("contract_package_hash", package.to_string());
+            event.insert("event_type", pair_event.type_name());
+            event.insert("pool_id", pool_id.to_string());
+            event.insert("reward_token", reward_token.to_string());
+            event.insert("amount", amount.to_string());
+            event.insert("reward_per_second", reward_per_second.to_string());
+            event.insert("period_finish", period_finish.to_string());
+            events.push(event);
+        }
+        StakingEvent::StreamRewardPaid {
+            user,
+            pool_id,
+            reward_token,
+            amount,
+        } => {
+            let mut event = BTreeMap::new();
+            event.insert("contract_package_hash", package.to_string());
+            event.insert("event_type", pair_event.type_name());
+            event.insert("user", user.to_string());
+            event.insert("pool_id", pool_id.to_string());
+            event.insert("reward_token", reward_token.to_string());
+            event.insert("amount", amount.to_string());
+            events.push(event);
+        }
+        StakingEvent::RewardFunded {
+            amount,
+            reward_per_second,
+            period_finish,
+        } => {
+            let mut event = BTreeMap::new();
+            event.insert("contract_package_hash", package.to_string());
+            event.insert("event_type", pair_event.type_name());
+            event.insert("amount", amount.to_string());
+            event.insert("reward_per_second", reward_per_second.to_string());
+            event.insert("period_finish", period_finish.to_string());
             events.push(event);
         }
         StakingEvent::Withdrawal {
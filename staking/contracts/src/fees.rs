@@ -0,0 +1,23 @@
+//! Deposit and withdrawal fees of the staking pools.
+//!
+//! Each pool can charge a fee in basis points on the LP tokens staked and unstaked, sent to the
+//! treasury. Stakes and the pool supply only record what is left after the fee.
+use casper_contract::contract_api::runtime;
+use casper_types::U256;
+
+use crate::error::Error;
+use crate::locks::BPS_DIVISOR;
+
+/// Highest deposit or withdrawal fee a pool can charge, 10%.
+pub const MAX_POOL_FEE_RATE: u64 = 1_000;
+
+pub(crate) fn validate_fee_rate(fee_rate: U256) {
+    if fee_rate > U256::from(MAX_POOL_FEE_RATE) {
+        runtime::revert(Error::InvalidPoolFeeRate);
+    }
+}
+
+/// Part of `amount` kept as a fee.
+pub(crate) fn pool_fee(amount: U256, fee_rate: U256) -> U256 {
+    amount * fee_rate / U256::from(BPS_DIVISOR)
+}
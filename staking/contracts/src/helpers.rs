@@ -284,6 +284,11 @@ pub(crate) fn get_named_arg_size(name: &str) -> Option<usize> {
 }
 
 
+/// Reads a named argument that callers may leave out, returning `None` when it is absent.
+pub(crate) fn get_optional_named_arg<T: CLTyped + FromBytes>(name: &str) -> Option<T> {
+    get_named_arg_size(name).map(|_| runtime::get_named_arg(name))
+}
+
 pub(crate) fn get_identifier_mode_from_runtime_args() -> NFTIdentifierMode {
     let identifier_mode_u8: u8 = runtime::get_named_arg(ARG_IDENTIFIER_MODE);
     let identifier_mode =
@@ -395,6 +400,16 @@ pub(crate) fn write_dictionary_value_from_key<T: CLTyped + FromBytes + ToBytes>(
     }
 }
 
+// Overwrites whatever is stored under `key`, even a value of another type
+pub(crate) fn put_dictionary_value_from_key<T: CLTyped + ToBytes>(
+    dictionary_name: &str,
+    key: &str,
+    value: T,
+) {
+    let seed_uref = get_uref(dictionary_name);
+    storage::dictionary_put(seed_uref, key, value);
+}
+
 pub(crate) fn get_unlock_id_key(unlock_id: &str) -> String {
     let unlock_id_bytes = unlock_id.as_bytes();
     let key_bytes = runtime::blake2b(unlock_id_bytes);
@@ -0,0 +1,53 @@
+//! Caps and allowlists of the staking pools.
+//!
+//! A pool can cap its total stake and the stake of each user, zero leaves either unlimited.
+//! Private pools only take stakes from the users on their allowlist, users taken off the list
+//! keep their stake and can still leave. Limits apply to the stake recorded, after the deposit
+//! fee.
+use casper_contract::{
+    contract_api::{runtime, storage},
+    unwrap_or_revert::UnwrapOrRevert,
+};
+use casper_types::{Key, U256};
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::{
+    get_dictionary_value_from_key, get_user_info_key, put_dictionary_value_from_key,
+};
+use crate::PoolInfo;
+
+/// Creates the dictionary of the pool allowlists, if it doesn't exist yet.
+pub(crate) fn create_allowlist_dictionary() {
+    if runtime::get_key(POOL_ALLOWLIST).is_none() {
+        storage::new_dictionary(POOL_ALLOWLIST)
+            .unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    }
+}
+
+pub(crate) fn is_allowed_staker(pool_id: u64, user: Key) -> bool {
+    get_dictionary_value_from_key::<bool>(POOL_ALLOWLIST, &get_user_info_key(pool_id, user))
+        .unwrap_or_default()
+}
+
+pub(crate) fn set_allowed_staker(pool_id: u64, user: Key, allowed: bool) {
+    put_dictionary_value_from_key(POOL_ALLOWLIST, &get_user_info_key(pool_id, user), allowed);
+}
+
+/// Reverts unless `user` can bring its stake to `user_stake` and the pool to `pool_stake`.
+pub(crate) fn check_stake_limits(
+    pool_info: &PoolInfo,
+    user: Key,
+    user_stake: U256,
+    pool_stake: U256,
+) {
+    if pool_info.private_pool && !is_allowed_staker(pool_info.pool_id, user) {
+        runtime::revert(Error::StakerNotAllowed);
+    }
+    if pool_info.max_pool_stake != U256::zero() && pool_stake > pool_info.max_pool_stake {
+        runtime::revert(Error::PoolStakeCapExceeded);
+    }
+    if pool_info.max_user_stake != U256::zero() && user_stake > pool_info.max_user_stake {
+        runtime::revert(Error::UserStakeCapExceeded);
+    }
+}
@@ -0,0 +1,75 @@
+//! Lock tiers of the staking pools.
+//!
+//! A stake can be locked for the duration of a tier, its share of the pool rewards is then
+//! multiplied by the tier's boost. Tier 0 is the unlocked tier without boost, tiers 1 and up are
+//! set by the owner with `set_lock_tiers`, longer tiers come later and boost at least as much.
+//! Durations use the unit of the block time, milliseconds.
+use alloc::vec::Vec;
+use casper_contract::contract_api::runtime;
+use casper_types::U256;
+
+use crate::constants::*;
+use crate::error::Error;
+use crate::helpers::get_key;
+
+/// Multipliers and penalty rates are in basis points.
+pub const BPS_DIVISOR: u64 = 10_000;
+/// Highest boost a tier can give, 5x.
+pub const MAX_LOCK_MULTIPLIER: u64 = 50_000;
+/// Highest share of an early withdrawal that can go to the treasury, 50%.
+pub const MAX_PENALTY_RATE: u64 = 5_000;
+
+pub(crate) struct LockTier {
+    pub duration: U256,
+    pub multiplier: U256,
+}
+
+pub(crate) fn get_lock_tier(lock_tier: u8) -> LockTier {
+    if lock_tier == 0 {
+        return LockTier {
+            duration: U256::zero(),
+            multiplier: U256::from(BPS_DIVISOR),
+        };
+    }
+
+    let durations: Vec<U256> = get_key(LOCK_TIER_DURATIONS).unwrap_or_default();
+    let multipliers: Vec<U256> = get_key(LOCK_TIER_MULTIPLIERS).unwrap_or_default();
+    let index = (lock_tier - 1) as usize;
+    match (durations.get(index), multipliers.get(index)) {
+        (Some(duration), Some(multiplier)) => LockTier {
+            duration: *duration,
+            multiplier: *multiplier,
+        },
+        _ => runtime::revert(Error::InvalidLockTier),
+    }
+}
+
+pub(crate) fn validate_lock_tiers(durations: &[U256], multipliers: &[U256]) {
+    if durations.len() != multipliers.len() || durations.len() > u8::MAX as usize {
+        runtime::revert(Error::InvalidLockTiers);
+    }
+
+    let mut previous = get_lock_tier(0);
+    for (duration, multiplier) in durations.iter().zip(multipliers.iter()) {
+        if *duration <= previous.duration
+            || *multiplier < previous.multiplier
+            || *multiplier > U256::from(MAX_LOCK_MULTIPLIER)
+        {
+            runtime::revert(Error::InvalidLockTiers);
+        }
+        previous = LockTier {
+            duration: *duration,
+            multiplier: *multiplier,
+        };
+    }
+}
+
+/// Share of the pool rewards earned by `amount` staked with `multiplier`.
+pub(crate) fn boosted_amount(amount: U256, multiplier: U256) -> U256 {
+    amount * multiplier / U256::from(BPS_DIVISOR)
+}
+
+/// Part of an early withdrawal kept by the treasury.
+pub(crate) fn early_withdraw_penalty(amount: U256, penalty_rate: U256) -> U256 {
+    amount * penalty_rate / U256::from(BPS_DIVISOR)
+}
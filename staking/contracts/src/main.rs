@@ -12,7 +12,12 @@ pub mod constants;
 mod entry_points;
 mod error;
 mod events;
+mod fees;
 mod helpers;
+mod emissions;
+mod limits;
+mod locks;
+mod streams;
 pub mod named_keys;
 use serde::{Deserialize, Serialize};
 
@@ -29,28 +34,35 @@ use casper_contract::{
     unwrap_or_revert::UnwrapOrRevert,
 };
 use casper_types::{
-    contracts::NamedKeys, runtime_args, ContractHash, ContractPackageHash, HashAddr, Key,
-    RuntimeArgs, U256, CLValue,
+    bytesrepr::{self, FromBytes, ToBytes},
+    contracts::NamedKeys,
+    runtime_args, CLType, CLTyped, CLValue, ContractHash, ContractPackageHash, HashAddr, Key,
+    RuntimeArgs, U256,
 };
 use events::StakingEvent;
+use streams::RewardStream;
 use helpers::{get_immediate_caller_key, get_self_key, get_user_info_key};
 
 // pub const u256_10_18 : U256 = U256::pow(U256::from("10"), U256::from("18"));
 pub const u256_10_18: u64 = u64::pow(10, 12);
 
-#[derive(Serialize, Deserialize, Clone)]
-pub(crate) struct TokenStake {
-    stake_amount: U256,
-    locked_from: U256,
-    locked_until: U256,
-}
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct UserInfo {
     total_stake_amount: U256,
-    reward_debt: U256,
+    reward_debt: U256, // on boosted_amount
     pending_rewards: U256,
-    // locked_until: U256,
-    // stakes: Vec<TokenStake>,
+    #[serde(default)]
+    boosted_amount: U256, // total_stake_amount times the lock multiplier
+    #[serde(default)]
+    lock_tier: u8,
+    #[serde(default)]
+    lock_multiplier: U256, // in basis points, kept from the tier at stake time
+    #[serde(default)]
+    locked_until: U256,
+    #[serde(default)]
+    stream_reward_debts: Vec<U256>, // one per reward stream of the pool
+    #[serde(default)]
+    stream_pending_rewards: Vec<U256>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -59,10 +71,174 @@ pub(crate) struct PoolInfo {
     pub lp_token: Key,
     pub alloc_point: U256,
     pub last_reward_second: U256,
-    pub acc_reward_per_share: U256,
-    // pub min_stake_duration: U256,
-    // pub early_withdraw_penalty_rate: U256,
+    pub acc_reward_per_share: U256, // per boosted share
+    #[serde(default)]
+    pub min_stake_duration: U256,
+    #[serde(default)]
+    pub early_withdraw_penalty_rate: U256, // in basis points, zero keeps locked stakes locked
     pub lp_supply: U256,
+    #[serde(default)]
+    pub boosted_supply: U256, // sum of the boosted amounts of the stakers
+    #[serde(default)]
+    pub reward_streams: Vec<RewardStream>,
+    #[serde(default)]
+    pub deposit_fee_rate: U256, // in basis points, like the withdraw fee
+    #[serde(default)]
+    pub withdraw_fee_rate: U256,
+    #[serde(default)]
+    pub max_pool_stake: U256, // zero for no cap, like max_user_stake
+    #[serde(default)]
+    pub max_user_stake: U256,
+    #[serde(default)]
+    pub private_pool: bool, // only users on the pool allowlist can stake
+}
+
+impl CLTyped for UserInfo {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for UserInfo {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.total_stake_amount.to_bytes()?);
+        result.append(&mut self.reward_debt.to_bytes()?);
+        result.append(&mut self.pending_rewards.to_bytes()?);
+        result.append(&mut self.boosted_amount.to_bytes()?);
+        result.append(&mut self.lock_tier.to_bytes()?);
+        result.append(&mut self.lock_multiplier.to_bytes()?);
+        result.append(&mut self.locked_until.to_bytes()?);
+        result.append(&mut self.stream_reward_debts.to_bytes()?);
+        result.append(&mut self.stream_pending_rewards.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.total_stake_amount.serialized_length()
+            + self.reward_debt.serialized_length()
+            + self.pending_rewards.serialized_length()
+            + self.boosted_amount.serialized_length()
+            + self.lock_tier.serialized_length()
+            + self.lock_multiplier.serialized_length()
+            + self.locked_until.serialized_length()
+            + self.stream_reward_debts.serialized_length()
+            + self.stream_pending_rewards.serialized_length()
+    }
+}
+
+impl FromBytes for UserInfo {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (total_stake_amount, remainder) = U256::from_bytes(bytes)?;
+        let (reward_debt, remainder) = U256::from_bytes(remainder)?;
+        let (pending_rewards, remainder) = U256::from_bytes(remainder)?;
+        let (boosted_amount, remainder) = U256::from_bytes(remainder)?;
+        let (lock_tier, remainder) = u8::from_bytes(remainder)?;
+        let (lock_multiplier, remainder) = U256::from_bytes(remainder)?;
+        let (locked_until, remainder) = U256::from_bytes(remainder)?;
+        let (stream_reward_debts, remainder) = Vec::<U256>::from_bytes(remainder)?;
+        let (stream_pending_rewards, remainder) = Vec::<U256>::from_bytes(remainder)?;
+        Ok((
+            UserInfo {
+                total_stake_amount,
+                reward_debt,
+                pending_rewards,
+                boosted_amount,
+                lock_tier,
+                lock_multiplier,
+                locked_until,
+                stream_reward_debts,
+                stream_pending_rewards,
+            },
+            remainder,
+        ))
+    }
+}
+
+impl CLTyped for PoolInfo {
+    fn cl_type() -> CLType {
+        CLType::Any
+    }
+}
+
+impl ToBytes for PoolInfo {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.pool_id.to_bytes()?);
+        result.append(&mut self.lp_token.to_bytes()?);
+        result.append(&mut self.alloc_point.to_bytes()?);
+        result.append(&mut self.last_reward_second.to_bytes()?);
+        result.append(&mut self.acc_reward_per_share.to_bytes()?);
+        result.append(&mut self.min_stake_duration.to_bytes()?);
+        result.append(&mut self.early_withdraw_penalty_rate.to_bytes()?);
+        result.append(&mut self.lp_supply.to_bytes()?);
+        result.append(&mut self.boosted_supply.to_bytes()?);
+        result.append(&mut self.reward_streams.to_bytes()?);
+        result.append(&mut self.deposit_fee_rate.to_bytes()?);
+        result.append(&mut self.withdraw_fee_rate.to_bytes()?);
+        result.append(&mut self.max_pool_stake.to_bytes()?);
+        result.append(&mut self.max_user_stake.to_bytes()?);
+        result.append(&mut self.private_pool.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.pool_id.serialized_length()
+            + self.lp_token.serialized_length()
+            + self.alloc_point.serialized_length()
+            + self.last_reward_second.serialized_length()
+            + self.acc_reward_per_share.serialized_length()
+            + self.min_stake_duration.serialized_length()
+            + self.early_withdraw_penalty_rate.serialized_length()
+            + self.lp_supply.serialized_length()
+            + self.boosted_supply.serialized_length()
+            + self.reward_streams.serialized_length()
+            + self.deposit_fee_rate.serialized_length()
+            + self.withdraw_fee_rate.serialized_length()
+            + self.max_pool_stake.serialized_length()
+            + self.max_user_stake.serialized_length()
+            + self.private_pool.serialized_length()
+    }
+}
+
+impl FromBytes for PoolInfo {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (pool_id, remainder) = u64::from_bytes(bytes)?;
+        let (lp_token, remainder) = Key::from_bytes(remainder)?;
+        let (alloc_point, remainder) = U256::from_bytes(remainder)?;
+        let (last_reward_second, remainder) = U256::from_bytes(remainder)?;
+        let (acc_reward_per_share, remainder) = U256::from_bytes(remainder)?;
+        let (min_stake_duration, remainder) = U256::from_bytes(remainder)?;
+        let (early_withdraw_penalty_rate, remainder) = U256::from_bytes(remainder)?;
+        let (lp_supply, remainder) = U256::from_bytes(remainder)?;
+        let (boosted_supply, remainder) = U256::from_bytes(remainder)?;
+        let (reward_streams, remainder) = Vec::<RewardStream>::from_bytes(remainder)?;
+        let (deposit_fee_rate, remainder) = U256::from_bytes(remainder)?;
+        let (withdraw_fee_rate, remainder) = U256::from_bytes(remainder)?;
+        let (max_pool_stake, remainder) = U256::from_bytes(remainder)?;
+        let (max_user_stake, remainder) = U256::from_bytes(remainder)?;
+        let (private_pool, remainder) = bool::from_bytes(remainder)?;
+        Ok((
+            PoolInfo {
+                pool_id,
+                lp_token,
+                alloc_point,
+                last_reward_second,
+                acc_reward_per_share,
+                min_stake_duration,
+                early_withdraw_penalty_rate,
+                lp_supply,
+                boosted_supply,
+                reward_streams,
+                deposit_fee_rate,
+                withdraw_fee_rate,
+                max_pool_stake,
+                max_user_stake,
+                private_pool,
+            },
+            remainder,
+        ))
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -103,6 +279,14 @@ pub extern "C" fn init() {
     runtime::put_key(START_BLOCK, storage::new_uref(start_second as U256).into());
     runtime::put_key(NUMBER_OF_POOL, storage::new_uref(0 as u64).into());
     runtime::put_key(TOTAL_ALLOC_POINT, storage::new_uref(U256::from("0")).into());
+    runtime::put_key(TREASURY, storage::new_uref(contract_owner).into());
+    runtime::put_key(LOCK_TIER_DURATIONS, storage::new_uref(Vec::<U256>::new()).into());
+    runtime::put_key(LOCK_TIER_MULTIPLIERS, storage::new_uref(Vec::<U256>::new()).into());
+    runtime::put_key(EMISSION_EPOCH_STARTS, storage::new_uref(Vec::<U256>::new()).into());
+    runtime::put_key(EMISSION_EPOCH_RATES, storage::new_uref(Vec::<U256>::new()).into());
+    runtime::put_key(EMISSION_END, storage::new_uref(U256::zero()).into());
+    runtime::put_key(FUNDED_REWARDS, storage::new_uref(false).into());
+    runtime::put_key(UNDISTRIBUTED_REWARDS, storage::new_uref(U256::zero()).into());
 
     // let init_pool_list = PoolList {
     //     all_pool: Vec::new(),
@@ -117,6 +301,7 @@ pub extern "C" fn init() {
     // storage::new_dictionary(TOKEN_STAKE).unwrap_or_revert_with(Error::FailedToCreateDictionary);
     storage::new_dictionary(USER_INFO).unwrap_or_revert_with(Error::FailedToCreateDictionary);
     storage::new_dictionary(POOL_INFO).unwrap_or_revert_with(Error::FailedToCreateDictionary);
+    limits::create_allowlist_dictionary();
 }
 
 #[no_mangle]
@@ -124,12 +309,22 @@ fn call() {
     let contract_name: String = runtime::get_named_arg(STAKING_CONTRACT_NAME);
     let contract_hash_key_name = String::from(contract_name.clone());
     let contract_package_hash_key_name = String::from(contract_name.clone() + "_package_name");
+    let access_uref_key_name = String::from(contract_name.clone() + "_access_uref");
+
+    // Running the session again from the installing account upgrades the package
+    if let Some(contract_package_hash_key) = runtime::get_key(&contract_package_hash_key_name) {
+        upgrade(contract_hash_key_name, contract_package_hash_key);
+        return;
+    }
+
     let contract_owner: Key = runtime::get_named_arg(ARG_CONTRACT_OWNER);
     let reward_token: Key = runtime::get_named_arg(ARG_REWARD_TOKEN);
     let reward_per_second: U256 = runtime::get_named_arg(ARG_REWARD_PER_SECOND);
     let start_second: U256 = runtime::get_named_arg(ARG_START_SECOND);
 
     let (contract_package_hash, access_uref) = storage::create_contract_package_at_hash();
+    runtime::put_key(&contract_package_hash_key_name, contract_package_hash.into());
+    runtime::put_key(&access_uref_key_name, access_uref.into());
 
     let named_keys: NamedKeys = named_keys::default(
         contract_owner,
@@ -159,40 +354,69 @@ fn call() {
     );
 }
 
+// Adds a contract version with the current entry points, the named keys of the previous version
+// carry over. The previous version is disabled, the owner converts legacy records afterwards with
+// migrate_storage.
+fn upgrade(contract_hash_key_name: String, contract_package_hash_key: Key) {
+    let contract_package_hash_addr: HashAddr =
+        contract_package_hash_key.into_hash().unwrap_or_revert();
+    let contract_package_hash = ContractPackageHash::new(contract_package_hash_addr);
+    let previous_contract_hash: Option<Key> = runtime::get_key(&contract_hash_key_name);
+
+    let (contract_hash, _version) = storage::add_contract_version(
+        contract_package_hash: ContractPackageHash,
+        entry_points::default(),
+        NamedKeys::new(),
+    );
+    if let Some(previous_contract_hash) = previous_contract_hash {
+        let previous_contract_hash_addr: HashAddr =
+            previous_contract_hash.into_hash().unwrap_or_revert();
+        storage::disable_contract_version(
+            contract_package_hash,
+            ContractHash::new(previous_contract_hash_addr),
+        )
+        .unwrap_or_revert();
+    }
+    runtime::put_key(contract_hash_key_name.as_str(), Key::from(contract_hash));
+}
+
 // this is userInfo
 
 fn get_user_info(pool_id: u64, user: Key) -> UserInfo {
     let user_info_key = get_user_info_key(pool_id, user);
-    let user_info: UserInfo;
-    // let user_info_str = get_dictionary_value_from_key::<String>(USER_INFO, &user_info_key);
-
-    if get_dictionary_value_from_key::<String>(USER_INFO, &user_info_key).is_some() {
-        let user_info_str =
-            get_dictionary_value_from_key::<String>(USER_INFO, &user_info_key).unwrap_or_revert();
-        user_info = casper_serde_json_wasm::from_str::<UserInfo>(&user_info_str).unwrap();
-
-        // if casper_serde_json_wasm::from_str::<UserInfo>(&user_info_str).is_err() {
-        //     runtime::revert(Error::CanNotGetUserInfo);
-        // } else {
-        //     let user_info_result = casper_serde_json_wasm::from_str::<UserInfo>(&user_info_str).unwrap();
-        //     user_info = UserInfo {
-        //         total_stake_amount: user_info_result.total_stake_amount,
-        //         reward_debt: user_info_result.reward_debt,
-        //         pending_rewards: user_info_result.pending_rewards,
-        //         locked_until: user_info_result.locked_until,
-        //         // stakes: user_info_result.stakes,
-        //     }
-        // };
-    } else {
-        user_info = UserInfo {
+
+    match get_user_info_by_key(&user_info_key) {
+        Some(user_info) => user_info,
+        None => UserInfo {
             total_stake_amount: U256::from("0"),
             reward_debt: U256::from("0"),
             pending_rewards: U256::from("0"),
-            // locked_until: U256::from("0"),
-            // stakes: Vec::new(),
-        }
+            boosted_amount: U256::from("0"),
+            lock_tier: 0,
+            lock_multiplier: U256::from(locks::BPS_DIVISOR),
+            locked_until: U256::from("0"),
+            stream_reward_debts: Vec::new(),
+            stream_pending_rewards: Vec::new(),
+        },
     }
-    user_info
+}
+
+// Records written before the bytesrepr encoding are still JSON strings until migrated, reading
+// them as bytesrepr fails on the type so they fall back to the JSON read
+fn get_user_info_by_key(user_info_key: &str) -> Option<UserInfo> {
+    get_dictionary_value_from_key::<UserInfo>(USER_INFO, user_info_key)
+        .or_else(|| get_legacy_user_info(user_info_key))
+}
+
+// Legacy records predate lock tiers, their whole stake counts unboosted
+fn get_legacy_user_info(user_info_key: &str) -> Option<UserInfo> {
+    get_dictionary_value_from_key::<String>(USER_INFO, user_info_key)
+        .and_then(|user_info_str| casper_serde_json_wasm::from_str::<UserInfo>(&user_info_str).ok())
+        .map(|mut user_info| {
+            user_info.boosted_amount = user_info.total_stake_amount;
+            user_info.lock_multiplier = U256::from(locks::BPS_DIVISOR);
+            user_info
+        })
 }
 
 // write user_info
@@ -204,26 +428,31 @@ fn write_dictionary_user_info(
 ) -> Result<(), Error> {
     let user_info_key = get_user_info_key(pool_id, user);
 
-    write_dictionary_value_from_key(
-        USER_INFO,
-        &user_info_key,
-        casper_serde_json_wasm::to_string_pretty(&new_user_info).unwrap(),
-    );
+    put_dictionary_value_from_key(USER_INFO, &user_info_key, new_user_info);
 
     Ok(())
 }
 
 // this is pool_info
 fn get_pool_info(pool_id: u64) -> PoolInfo {
-    let pool_info_str: String =
-        helpers::get_dictionary_value_from_key(POOL_INFO, &pool_id.clone().to_string()).unwrap();
-    if casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).is_err() {
-        runtime::revert(Error::CanNotGetPoolList)
-    }
+    let pool_info_key = pool_id.to_string();
+    get_dictionary_value_from_key::<PoolInfo>(POOL_INFO, &pool_info_key)
+        .or_else(|| get_legacy_pool_info(&pool_info_key))
+        .unwrap_or_revert_with(Error::CanNotGetPoolList)
+}
 
-    let pool_info = casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).unwrap();
+fn get_legacy_pool_info(pool_info_key: &str) -> Option<PoolInfo> {
+    get_dictionary_value_from_key::<String>(POOL_INFO, pool_info_key)
+        .and_then(|pool_info_str| casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).ok())
+        .map(|mut pool_info| {
+            pool_info.boosted_supply = pool_info.lp_supply;
+            pool_info
+        })
+}
 
-    pool_info
+// write pool_info
+fn write_dictionary_pool_info(pool_info: PoolInfo) {
+    put_dictionary_value_from_key(POOL_INFO, &pool_info.pool_id.to_string(), pool_info);
 }
 
 // Add new pool for new lp_token
@@ -260,9 +489,16 @@ pub extern "C" fn add_new_pool2() -> Result<(), Error> {
         alloc_point: alloc_point,
         last_reward_second: last_reward_second,
         acc_reward_per_share: acc_reward_per_block,
-        // min_stake_duration: min_stake_duration,
-        // early_withdraw_penalty_rate: early_withdraw_penalty_rate,
+        min_stake_duration: U256::from("0"),
+        early_withdraw_penalty_rate: U256::from("0"),
         lp_supply: U256::from("0"),
+        boosted_supply: U256::from("0"),
+        reward_streams: Vec::new(),
+        deposit_fee_rate: U256::from("0"),
+        withdraw_fee_rate: U256::from("0"),
+        max_pool_stake: U256::from("0"),
+        max_user_stake: U256::from("0"),
+        private_pool: false,
     };
 
     let current_list_str: String = helpers::get_stored_value_with_user_errors(
@@ -350,19 +586,43 @@ pub extern "C" fn add_new_pool() -> Result<(), Error> {
 
     let last_reward_second: U256 = runtime::get_named_arg(ARG_LAST_REWARD_SECOND);
     let acc_reward_per_block: U256 = runtime::get_named_arg(ARG_ACC_REWARD_PER_SHARE);
-    // let min_stake_duration: U256 = runtime::get_named_arg(ARG_MIN_STAKE_DURATION);
-    // let early_withdraw_penalty_rate: U256 = runtime::get_named_arg(ARG_PENALTY_RATE);
+    // Pools without a minimum duration accept unlocked stakes, without a penalty rate locks are final
+    let min_stake_duration: U256 =
+        get_optional_named_arg(ARG_MIN_STAKE_DURATION).unwrap_or_default();
+    let early_withdraw_penalty_rate: U256 =
+        get_optional_named_arg(ARG_PENALTY_RATE).unwrap_or_default();
+    if early_withdraw_penalty_rate > U256::from(locks::MAX_PENALTY_RATE) {
+        runtime::revert(Error::InvalidPenaltyRate);
+    }
+    let deposit_fee_rate: U256 = get_optional_named_arg(ARG_DEPOSIT_FEE_RATE).unwrap_or_default();
+    let withdraw_fee_rate: U256 =
+        get_optional_named_arg(ARG_WITHDRAW_FEE_RATE).unwrap_or_default();
+    fees::validate_fee_rate(deposit_fee_rate);
+    fees::validate_fee_rate(withdraw_fee_rate);
+    let max_pool_stake: U256 = get_optional_named_arg(ARG_MAX_POOL_STAKE).unwrap_or_default();
+    let max_user_stake: U256 = get_optional_named_arg(ARG_MAX_USER_STAKE).unwrap_or_default();
+    let private_pool: bool = get_optional_named_arg(ARG_PRIVATE_POOL).unwrap_or_default();
     let new_pool = PoolInfo {
         pool_id: current_number_of_pool.clone(),
         lp_token: lp_contract_hash,
         alloc_point: alloc_point,
         last_reward_second: last_reward_second,
         acc_reward_per_share: acc_reward_per_block,
-        // min_stake_duration: min_stake_duration,
-        // early_withdraw_penalty_rate: early_withdraw_penalty_rate,
+        min_stake_duration: min_stake_duration,
+        early_withdraw_penalty_rate: early_withdraw_penalty_rate,
         lp_supply: U256::from("0"),
+        boosted_supply: U256::from("0"),
+        reward_streams: Vec::new(),
+        deposit_fee_rate: deposit_fee_rate,
+        withdraw_fee_rate: withdraw_fee_rate,
+        max_pool_stake: max_pool_stake,
+        max_user_stake: max_user_stake,
+        private_pool: private_pool,
     };
 
+    // The new pool dilutes the others from now on, not retroactively
+    update_all_pools();
+
     // let current_list_str: String = helpers::get_stored_value_with_user_errors(
     //     POOL_LIST,
     //     Error::MissingPoolList,
@@ -395,11 +655,7 @@ pub extern "C" fn add_new_pool() -> Result<(), Error> {
     //     POOL_INFO,
     //     casper_serde_json_wasm::to_string_pretty(&new_pool).unwrap(),
     // );
-    write_dictionary_value_from_key(
-        POOL_INFO,
-        &current_number_of_pool.clone().to_string(),
-        casper_serde_json_wasm::to_string_pretty(&new_pool).unwrap(),
-    );
+    write_dictionary_pool_info(new_pool);
 
     set_key(TOTAL_ALLOC_POINT, new_total_alloc_point);
     set_key(NUMBER_OF_POOL, current_number_of_pool + 1);
@@ -431,7 +687,33 @@ pub extern "C" fn stake() -> Result<(), Error> {
     if amount <= U256::from("0") {
         runtime::revert(Error::InvalidAmount);
     }
-    // let stake_duration: U256 = runtime::get_named_arg(ARG_STAKE_DURATION);
+    // Leaving the tier out keeps the running lock, like stake_for
+    let lock_tier: Option<u8> = get_optional_named_arg(ARG_LOCK_TIER);
+    let caller = get_immediate_caller_key();
+
+    stake_for_user(caller, caller, pool_id, amount, lock_tier);
+    Ok(())
+}
+
+// Stake LP token of the caller on behalf of a beneficiary, which gets the stake, its rewards and
+// the right to withdraw it. The beneficiary's lock is never set or extended.
+#[no_mangle]
+pub extern "C" fn stake_for() -> Result<(), Error> {
+    let pool_id: u64 = runtime::get_named_arg(ARG_POOL_ID);
+    let beneficiary: Key = runtime::get_named_arg(ARG_BENEFICIARY);
+    let amount: U256 = runtime::get_named_arg(ARG_AMOUNT);
+    if amount <= U256::from("0") {
+        runtime::revert(Error::InvalidAmount);
+    }
+    let caller = get_immediate_caller_key();
+
+    stake_for_user(caller, beneficiary, pool_id, amount, None);
+    Ok(())
+}
+
+// Stakes LP token of `payer` for `user`. Without a lock tier the user's running lock is kept as
+// is, or the stake is unlocked when there is none.
+fn stake_for_user(payer: Key, user: Key, pool_id: u64, amount: U256, lock_tier: Option<u8>) {
     let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
         REWARD_TOKEN,
         Error::MissingRewardToken,
@@ -442,30 +724,46 @@ pub extern "C" fn stake() -> Result<(), Error> {
     // update pool with pool_id
     update_pool(rewards_token, pool_id.clone());
 
-    let caller = get_immediate_caller_key();
-
     // get pool_info
 
-    let pool_info_str: String =
-        helpers::get_dictionary_value_from_key(POOL_INFO, &pool_id.to_string()).unwrap();
-    // if casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).is_err() {
-    //     runtime::revert(Error::CanNotGetPoolList)
-    // }
-
-    let mut pool_info = casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).unwrap();
-
-    // // Check if stake_duration is
-    // if stake_duration < pool_info.min_stake_duration {
-    //     runtime::revert(Error::InvalidStakeDuration);
-    // }
-    // Todo: write to USER_INFO (userInfo)
+    let mut pool_info = get_pool_info(pool_id);
 
     let this_key: Key = get_self_key();
-
-    let mut user_info_of_this_pool = get_user_info(pool_id, caller);
+    let now = U256::from(current_block_timestamp());
+
+    let mut user_info_of_this_pool = get_user_info(pool_id, user);
+    match lock_tier {
+        Some(lock_tier) => {
+            let tier = locks::get_lock_tier(lock_tier);
+            if tier.duration < pool_info.min_stake_duration {
+                runtime::revert(Error::InvalidStakeDuration);
+            }
+            // A running lock can only be extended, with its own tier or a longer one
+            if user_info_of_this_pool.locked_until > now
+                && lock_tier < user_info_of_this_pool.lock_tier
+            {
+                runtime::revert(Error::InvalidLockTier);
+            }
+            // The whole stake moves to the tier, the lock ends with the later of the two
+            if now + tier.duration > user_info_of_this_pool.locked_until {
+                user_info_of_this_pool.locked_until = now + tier.duration;
+            }
+            user_info_of_this_pool.lock_tier = lock_tier;
+            user_info_of_this_pool.lock_multiplier = tier.multiplier;
+        }
+        None => {
+            if user_info_of_this_pool.locked_until <= now {
+                if pool_info.min_stake_duration > U256::zero() {
+                    runtime::revert(Error::InvalidStakeDuration);
+                }
+                user_info_of_this_pool.lock_tier = 0;
+                user_info_of_this_pool.lock_multiplier = U256::from(locks::BPS_DIVISOR);
+            }
+        }
+    }
     if user_info_of_this_pool.total_stake_amount > U256::from("0") {
         // pay pending rewards
-        let pending_rewards: U256 = (user_info_of_this_pool.total_stake_amount.clone()
+        let pending_rewards: U256 = (user_info_of_this_pool.boosted_amount.clone()
             * pool_info.acc_reward_per_share.clone()
             / U256::from(u256_10_18))
             - user_info_of_this_pool.reward_debt;
@@ -474,45 +772,51 @@ pub extern "C" fn stake() -> Result<(), Error> {
         user_info_of_this_pool = pay_rewards(
             rewards_token,
             this_key,
-            caller,
+            user,
             pending_rewards,
             user_info_of_this_pool.clone(),
         );
     }
+    // Stream rewards stay pending until harvested, a failing partner token can't block staking
+    streams::accrue_stream_rewards(&mut user_info_of_this_pool, &pool_info);
     // Call transfer_from function to transfer deposit to this contract
 
     let lp_token_hash = pool_info.lp_token;
     // transfer lp_token to the pool
-    transfer_from_erc20_token(lp_token_hash, caller, this_key, amount.clone());
+    transfer_from_erc20_token(lp_token_hash, payer, this_key, amount.clone());
+    // The deposit fee goes to the treasury, the stake records what is left
+    let deposit_fee: U256 = fees::pool_fee(amount, pool_info.deposit_fee_rate);
+    charge_pool_fee(lp_token_hash, user, pool_id, deposit_fee);
+    let net_amount: U256 = amount - deposit_fee;
+    limits::check_stake_limits(
+        &pool_info,
+        user,
+        user_info_of_this_pool.total_stake_amount + net_amount,
+        pool_info.lp_supply + net_amount,
+    );
 
     // Recalculate  user_info and pool_info
     user_info_of_this_pool.total_stake_amount =
-        user_info_of_this_pool.total_stake_amount.clone() + amount.clone();
-    pool_info.lp_supply = pool_info.lp_supply.clone() + amount.clone();
-
-    // Todo: rewards debt
+        user_info_of_this_pool.total_stake_amount.clone() + net_amount;
+    pool_info.lp_supply = pool_info.lp_supply.clone() + net_amount;
+    update_boosted_amount(&mut user_info_of_this_pool, &mut pool_info);
 
-    user_info_of_this_pool.reward_debt = user_info_of_this_pool.total_stake_amount.clone()
-        * pool_info.acc_reward_per_share.clone()
-        / U256::from(u256_10_18);
+    let lock_tier = user_info_of_this_pool.lock_tier;
+    let locked_until = user_info_of_this_pool.locked_until;
 
     // save
-    write_dictionary_user_info(pool_id, caller, user_info_of_this_pool);
+    write_dictionary_user_info(pool_id, user, user_info_of_this_pool);
 
-    write_dictionary_value_from_key(
-        POOL_INFO,
-        &pool_id.clone().to_string(),
-        casper_serde_json_wasm::to_string_pretty(&pool_info).unwrap(),
-    );
+    write_dictionary_pool_info(pool_info);
     // Emit event
     events::emit(&StakingEvent::UserStake {
-        user: caller.clone(),
+        user: user,
+        staked_by: payer,
         lp_token: lp_token_hash.clone(),
-        amount: amount.clone(),
-        // stake_duration: stake_duration.clone(),
+        amount: net_amount,
+        lock_tier: lock_tier,
+        locked_until: locked_until,
     });
-
-    Ok(())
 }
 
 // Stake LP token
@@ -727,13 +1031,7 @@ pub extern "C" fn un_stake() -> Result<(), Error> {
 
     // get pool_info
 
-    let pool_info_str: String =
-        helpers::get_dictionary_value_from_key(POOL_INFO, &pool_id.clone().to_string()).unwrap();
-    if casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).is_err() {
-        runtime::revert(Error::CanNotGetPoolList)
-    }
-
-    let mut pool_info = casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).unwrap();
+    let mut pool_info = get_pool_info(pool_id);
 
     let this_key: Key = get_self_key();
 
@@ -744,7 +1042,17 @@ pub extern "C" fn un_stake() -> Result<(), Error> {
         runtime::revert(Error::InvalidAmount);
     }
 
-    let pending: U256 = user_info_of_this_pool.total_stake_amount * pool_info.acc_reward_per_share
+    // Leaving a lock early costs the pool's penalty, pools without one keep the stake locked
+    let now = U256::from(current_block_timestamp());
+    let mut penalty = U256::zero();
+    if user_info_of_this_pool.locked_until > now {
+        if pool_info.early_withdraw_penalty_rate == U256::zero() {
+            runtime::revert(Error::StakeLocked);
+        }
+        penalty = locks::early_withdraw_penalty(amount, pool_info.early_withdraw_penalty_rate);
+    }
+
+    let pending: U256 = user_info_of_this_pool.boosted_amount * pool_info.acc_reward_per_share
         / U256::from(u256_10_18)
         - user_info_of_this_pool.reward_debt;
     let total_pending: U256 = user_info_of_this_pool.pending_rewards + pending;
@@ -756,21 +1064,38 @@ pub extern "C" fn un_stake() -> Result<(), Error> {
         total_pending,
         user_info_of_this_pool,
     );
+    // Earned stream rewards stay pending, harvest pays them
+    streams::accrue_stream_rewards(&mut user_info_of_this_pool, &pool_info);
     user_info_of_this_pool.total_stake_amount =
         user_info_of_this_pool.total_stake_amount.clone() - amount.clone();
     pool_info.lp_supply = pool_info.lp_supply.clone() - amount.clone();
-    transfer_erc20_token(pool_info.lp_token.clone(), caller, amount);
-    user_info_of_this_pool.reward_debt = user_info_of_this_pool.total_stake_amount
-        * pool_info.acc_reward_per_share
-        / U256::from(u256_10_18);
+    let withdraw_fee: U256 = fees::pool_fee(amount, pool_info.withdraw_fee_rate);
+    transfer_erc20_token(pool_info.lp_token.clone(), caller, amount - penalty - withdraw_fee);
+    charge_pool_fee(pool_info.lp_token, caller, pool_id, withdraw_fee);
+    if penalty > U256::zero() {
+        let treasury: Key = helpers::get_stored_value_with_user_errors(
+            TREASURY,
+            Error::MissingTreasury,
+            Error::InvalidTreasury,
+        );
+        transfer_erc20_token(pool_info.lp_token.clone(), treasury, penalty);
+        events::emit(&StakingEvent::EarlyWithdrawPenalty {
+            user: caller,
+            pool_id: pool_id,
+            treasury: treasury,
+            penalty: penalty,
+        });
+    }
+    // An expired lock loses its boost
+    if user_info_of_this_pool.locked_until <= now {
+        user_info_of_this_pool.lock_tier = 0;
+        user_info_of_this_pool.lock_multiplier = U256::from(locks::BPS_DIVISOR);
+    }
+    update_boosted_amount(&mut user_info_of_this_pool, &mut pool_info);
 
     // save
     write_dictionary_user_info(pool_id, caller, user_info_of_this_pool);
-    write_dictionary_value_from_key(
-        POOL_INFO,
-        &pool_id.clone().to_string(),
-        casper_serde_json_wasm::to_string_pretty(&pool_info).unwrap(),
-    );
+    write_dictionary_pool_info(pool_info);
 
     // Emit event
     events::emit(&StakingEvent::UnStake {
@@ -782,6 +1107,295 @@ pub extern "C" fn un_stake() -> Result<(), Error> {
     Ok(())
 }
 
+// Returns the caller's whole stake without calling the reward token, for when update_pool or
+// pay_rewards keep reverting. Pending rewards are forfeited. Locks still apply: a locked stake
+// pays the pool's early withdraw penalty, or can't leave before its end in pools without one.
+#[no_mangle]
+pub extern "C" fn emergency_withdraw() -> Result<(), Error> {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+
+    let caller = get_immediate_caller_key();
+    let mut pool_info = get_pool_info(pool_id);
+    let user_info_of_this_pool = get_user_info(pool_id, caller);
+    let amount: U256 = user_info_of_this_pool.total_stake_amount;
+    if amount == U256::zero() {
+        runtime::revert(Error::NothingStaked);
+    }
+
+    let now = U256::from(current_block_timestamp());
+    let mut penalty = U256::zero();
+    if user_info_of_this_pool.locked_until > now {
+        if pool_info.early_withdraw_penalty_rate == U256::zero() {
+            runtime::revert(Error::StakeLocked);
+        }
+        penalty = locks::early_withdraw_penalty(amount, pool_info.early_withdraw_penalty_rate);
+    }
+
+    pool_info.lp_supply = pool_info.lp_supply - amount;
+    pool_info.boosted_supply = pool_info.boosted_supply - user_info_of_this_pool.boosted_amount;
+    let lp_token = pool_info.lp_token;
+    let withdraw_fee: U256 = fees::pool_fee(amount, pool_info.withdraw_fee_rate);
+
+    write_dictionary_user_info(pool_id, caller, UserInfo {
+        total_stake_amount: U256::zero(),
+        reward_debt: U256::zero(),
+        pending_rewards: U256::zero(),
+        boosted_amount: U256::zero(),
+        lock_tier: 0,
+        lock_multiplier: U256::from(locks::BPS_DIVISOR),
+        locked_until: U256::zero(),
+        stream_reward_debts: Vec::new(),
+        stream_pending_rewards: Vec::new(),
+    });
+    write_dictionary_pool_info(pool_info);
+
+    transfer_erc20_token(lp_token, caller, amount - penalty - withdraw_fee);
+    charge_pool_fee(lp_token, caller, pool_id, withdraw_fee);
+    if penalty > U256::zero() {
+        let treasury: Key = helpers::get_stored_value_with_user_errors(
+            TREASURY,
+            Error::MissingTreasury,
+            Error::InvalidTreasury,
+        );
+        transfer_erc20_token(lp_token, treasury, penalty);
+        events::emit(&StakingEvent::EarlyWithdrawPenalty {
+            user: caller,
+            pool_id: pool_id,
+            treasury: treasury,
+            penalty: penalty,
+        });
+    }
+
+    events::emit(&StakingEvent::EmergencyWithdraw {
+        user: caller,
+        pool_id: pool_id,
+        amount: amount,
+    });
+    Ok(())
+}
+
+// Claims the pending rewards of the caller in a pool, its stake stays untouched
+#[no_mangle]
+pub extern "C" fn harvest() -> Result<(), Error> {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
+        REWARD_TOKEN,
+        Error::MissingRewardToken,
+        Error::InvalidRewardToken,
+    );
+    let caller = get_immediate_caller_key();
+
+    update_pool(rewards_token, pool_id);
+    harvest_pool(rewards_token, pool_id, caller);
+    Ok(())
+}
+
+// Claims the pending rewards of the caller in every pool it has a position in
+#[no_mangle]
+pub extern "C" fn harvest_all() -> Result<(), Error> {
+    let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
+        REWARD_TOKEN,
+        Error::MissingRewardToken,
+        Error::InvalidRewardToken,
+    );
+    let number_of_pool: u64 = helpers::get_stored_value_with_user_errors(
+        NUMBER_OF_POOL,
+        Error::MissingNumberOfPool,
+        Error::InvalidNumberOfPool,
+    );
+    let caller = get_immediate_caller_key();
+
+    for pool_id in 0..number_of_pool {
+        let user_info_of_this_pool = get_user_info(pool_id, caller);
+        if user_info_of_this_pool.total_stake_amount == U256::zero()
+            && user_info_of_this_pool.pending_rewards == U256::zero()
+            && user_info_of_this_pool
+                .stream_pending_rewards
+                .iter()
+                .all(|pending| pending.is_zero())
+        {
+            continue;
+        }
+        update_pool(rewards_token, pool_id);
+        harvest_pool(rewards_token, pool_id, caller);
+    }
+    Ok(())
+}
+
+// Restakes the pending rewards of the caller, for pools staking the reward token itself. The
+// rewards are already held by this contract, minted by update_pool or deposited with
+// notify_reward_amount, so nothing is transferred. They join the caller's current lock. Only
+// rewards the contract's reward token balance covers are restaked, the rest stays pending.
+#[no_mangle]
+pub extern "C" fn compound() -> Result<(), Error> {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
+        REWARD_TOKEN,
+        Error::MissingRewardToken,
+        Error::InvalidRewardToken,
+    );
+    let caller = get_immediate_caller_key();
+
+    if get_pool_info(pool_id).lp_token != rewards_token {
+        runtime::revert(Error::CannotCompound);
+    }
+    update_pool(rewards_token, pool_id);
+
+    let mut pool_info = get_pool_info(pool_id);
+    let mut user_info_of_this_pool = get_user_info(pool_id, caller);
+    let accrued: U256 = get_accrued_rewards(&user_info_of_this_pool, &pool_info);
+    let amount: U256 = core::cmp::min(accrued, get_available_rewards(rewards_token));
+    if amount == U256::zero() {
+        runtime::revert(Error::InvalidAmount);
+    }
+
+    limits::check_stake_limits(
+        &pool_info,
+        caller,
+        user_info_of_this_pool.total_stake_amount + amount,
+        pool_info.lp_supply + amount,
+    );
+
+    // Stream rewards are in other tokens, they stay pending until harvested
+    streams::accrue_stream_rewards(&mut user_info_of_this_pool, &pool_info);
+    user_info_of_this_pool.pending_rewards = accrued - amount;
+    user_info_of_this_pool.total_stake_amount = user_info_of_this_pool.total_stake_amount + amount;
+    pool_info.lp_supply = pool_info.lp_supply + amount;
+    update_boosted_amount(&mut user_info_of_this_pool, &mut pool_info);
+
+    write_dictionary_user_info(pool_id, caller, user_info_of_this_pool);
+    write_dictionary_pool_info(pool_info);
+
+    events::emit(&StakingEvent::Compound {
+        user: caller,
+        pool_id: pool_id,
+        amount: amount,
+    });
+    Ok(())
+}
+
+// Rewards a user earned in a pool and hasn't been paid yet, update_pool must have run before
+fn get_accrued_rewards(user_info: &UserInfo, pool_info: &PoolInfo) -> U256 {
+    user_info.boosted_amount * pool_info.acc_reward_per_share / U256::from(u256_10_18)
+        - user_info.reward_debt
+        + user_info.pending_rewards
+}
+
+// Reward tokens held by this contract that aren't stake, the pools staking the reward token keep
+// their stake in the same balance
+fn get_available_rewards(rewards_token: Key) -> U256 {
+    let balance: U256 = get_balance_erc20_token(rewards_token, get_self_key());
+    let number_of_pool: u64 = helpers::get_stored_value_with_user_errors(
+        NUMBER_OF_POOL,
+        Error::MissingNumberOfPool,
+        Error::InvalidNumberOfPool,
+    );
+    let mut staked = U256::zero();
+    for pool_id in 0..number_of_pool {
+        let pool_info = get_pool_info(pool_id);
+        if pool_info.lp_token == rewards_token {
+            staked = staked + pool_info.lp_supply;
+        }
+    }
+    balance.saturating_sub(staked)
+}
+
+// Pays what a user earned in a pool, whatever the contract can't cover stays pending
+fn harvest_pool(rewards_token: Key, pool_id: u64, user: Key) {
+    let pool_info = get_pool_info(pool_id);
+    let mut user_info_of_this_pool = get_user_info(pool_id, user);
+    streams::accrue_stream_rewards(&mut user_info_of_this_pool, &pool_info);
+    pay_stream_rewards(user, pool_id, &mut user_info_of_this_pool, &pool_info);
+
+    let total_pending: U256 = get_accrued_rewards(&user_info_of_this_pool, &pool_info);
+    let mut paid = U256::zero();
+    if total_pending > U256::zero() {
+        user_info_of_this_pool.pending_rewards = total_pending;
+        user_info_of_this_pool = pay_rewards(
+            rewards_token,
+            get_self_key(),
+            user,
+            total_pending,
+            user_info_of_this_pool,
+        );
+        user_info_of_this_pool.reward_debt = user_info_of_this_pool.boosted_amount
+            * pool_info.acc_reward_per_share
+            / U256::from(u256_10_18);
+        paid = total_pending - user_info_of_this_pool.pending_rewards;
+    }
+    write_dictionary_user_info(pool_id, user, user_info_of_this_pool);
+
+    if paid > U256::zero() {
+        events::emit(&StakingEvent::Harvest {
+            user: user,
+            pool_id: pool_id,
+            amount: paid,
+        });
+    }
+}
+
+// Pays the stream rewards a user has pending in a pool, whatever the contract can't cover stays
+// pending
+fn pay_stream_rewards(user: Key, pool_id: u64, user_info: &mut UserInfo, pool_info: &PoolInfo) {
+    let this_contract = get_self_key();
+    for (stream, pending) in pool_info
+        .reward_streams
+        .iter()
+        .zip(user_info.stream_pending_rewards.iter_mut())
+    {
+        if pending.is_zero() {
+            continue;
+        }
+        let balance: U256 = get_balance_erc20_token(stream.reward_token, this_contract);
+        let paid = core::cmp::min(*pending, balance);
+        if paid.is_zero() {
+            continue;
+        }
+        transfer_erc20_token(stream.reward_token, user, paid);
+        *pending = *pending - paid;
+        events::emit(&StakingEvent::StreamRewardPaid {
+            user: user,
+            pool_id: pool_id,
+            reward_token: stream.reward_token,
+            amount: paid,
+        });
+    }
+}
+
+// Sends a deposit or withdrawal fee, already held by this contract, to the treasury
+fn charge_pool_fee(lp_token: Key, user: Key, pool_id: u64, fee: U256) {
+    if fee == U256::zero() {
+        return;
+    }
+    let treasury: Key = helpers::get_stored_value_with_user_errors(
+        TREASURY,
+        Error::MissingTreasury,
+        Error::InvalidTreasury,
+    );
+    transfer_erc20_token(lp_token, treasury, fee);
+    events::emit(&StakingEvent::PoolFeeCharged {
+        user: user,
+        pool_id: pool_id,
+        treasury: treasury,
+        fee: fee,
+    });
+}
+
 fn pay_rewards(
     rewards_token: Key,
     this_contract: Key,
@@ -809,70 +1423,239 @@ fn pay_rewards(
     user_info_of_this_pool
 }
 
-fn get_multiplier(from: U256, to: U256) -> U256 {
-    let multiplier = to - from;
-    multiplier
+// Recomputes the boosted share of a user after its stake or multiplier changed, and settles its
+// reward debt on it. Pending rewards must be accounted for before.
+fn update_boosted_amount(user_info: &mut UserInfo, pool_info: &mut PoolInfo) {
+    let boosted_amount = locks::boosted_amount(user_info.total_stake_amount, user_info.lock_multiplier);
+    pool_info.boosted_supply = pool_info.boosted_supply - user_info.boosted_amount + boosted_amount;
+    user_info.boosted_amount = boosted_amount;
+    user_info.reward_debt =
+        user_info.boosted_amount * pool_info.acc_reward_per_share / U256::from(u256_10_18);
+    streams::reset_stream_debts(user_info, pool_info);
 }
-fn update_pool(rewards_token: Key, pool_id: u64) {
-    // get Pool_Info of this pool
-
-    let pool_info_str: String =
-        helpers::get_dictionary_value_from_key(POOL_INFO, &pool_id.to_string()).unwrap();
-    // if casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).is_err() {
-    //     runtime::revert(Error::CanNotGetPoolList)
-    // }
 
-    let mut this_pool = casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).unwrap();
-
-    // get current block stamps
-    let current_block_timestamps: U256 = U256::from(current_block_timestamp());
-
-    if current_block_timestamps <= this_pool.last_reward_second {
-        runtime::revert(Error::InvalidContext);
-    }
-
-    // Todo: update pool
-    let multiplier: U256 = get_multiplier(this_pool.last_reward_second, current_block_timestamps);
-    let total_alloc_point: U256 = helpers::get_stored_value_with_user_errors::<U256>(
-        TOTAL_ALLOC_POINT,
-        Error::MissingTotalAllocPoint,
-        Error::InvalidTotalAllocPoint,
+// Brings every pool up to date, admin changes to the emission call this first so rewards already
+// earned keep the old rate and weights
+fn update_all_pools() {
+    let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
+        REWARD_TOKEN,
+        Error::MissingRewardToken,
+        Error::InvalidRewardToken,
     );
-    let reward_per_second: U256 = helpers::get_stored_value_with_user_errors::<U256>(
-        REWARD_PER_SECOND,
-        Error::MissingRewardPerSecond,
-        Error::InvalidRewardPerSecond,
+    let number_of_pool: u64 = helpers::get_stored_value_with_user_errors(
+        NUMBER_OF_POOL,
+        Error::MissingNumberOfPool,
+        Error::InvalidNumberOfPool,
     );
+    for pool_id in 0..number_of_pool {
+        update_pool(rewards_token, pool_id);
+    }
+}
 
-    if total_alloc_point > U256::from("0") && this_pool.lp_supply != U256::from("0") {
-        let reward: U256 =
-            multiplier * reward_per_second * this_pool.alloc_point / total_alloc_point;
-        // Todo: transfer reward to this contract
-
-        let contract_key: Key = get_self_key();
-
-        // mint reward
+#[no_mangle]
+pub extern "C" fn mass_update_pools() -> Result<(), Error> {
+    update_all_pools();
+    Ok(())
+}
 
-        // mint_reward_to_contract(contract_key, reward);
+// Changes the share of the emission a pool gets
+#[no_mangle]
+pub extern "C" fn set_pool() -> Result<(), Error> {
+    let current_contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let alloc_point: U256 = runtime::get_named_arg(ARG_ALLOC_POINT);
+
+    update_all_pools();
+
+    let mut pool_info = get_pool_info(pool_id);
+    let total_alloc_point: U256 = helpers::get_stored_value_with_user_errors::<U256>(
+        TOTAL_ALLOC_POINT,
+        Error::MissingTotalAllocPoint,
+        Error::InvalidTotalAllocPoint,
+    );
+    set_key(
+        TOTAL_ALLOC_POINT,
+        total_alloc_point - pool_info.alloc_point + alloc_point,
+    );
+    pool_info.alloc_point = alloc_point;
+    write_dictionary_pool_info(pool_info);
+    Ok(())
+}
+
+// Changes the deposit and withdrawal fees of a pool, both in basis points
+#[no_mangle]
+pub extern "C" fn set_pool_fees() -> Result<(), Error> {
+    let current_contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let deposit_fee_rate: U256 = runtime::get_named_arg(ARG_DEPOSIT_FEE_RATE);
+    let withdraw_fee_rate: U256 = runtime::get_named_arg(ARG_WITHDRAW_FEE_RATE);
+    fees::validate_fee_rate(deposit_fee_rate);
+    fees::validate_fee_rate(withdraw_fee_rate);
+
+    let mut pool_info = get_pool_info(pool_id);
+    pool_info.deposit_fee_rate = deposit_fee_rate;
+    pool_info.withdraw_fee_rate = withdraw_fee_rate;
+    write_dictionary_pool_info(pool_info);
+    Ok(())
+}
+
+// Caps the total stake of a pool and the stake of each user, zero removes a cap. Stakes above a
+// lowered cap stay, they can't grow.
+#[no_mangle]
+pub extern "C" fn set_pool_limits() -> Result<(), Error> {
+    let current_contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let mut pool_info = get_pool_info(pool_id);
+    pool_info.max_pool_stake = runtime::get_named_arg(ARG_MAX_POOL_STAKE);
+    pool_info.max_user_stake = runtime::get_named_arg(ARG_MAX_USER_STAKE);
+    pool_info.private_pool = runtime::get_named_arg(ARG_PRIVATE_POOL);
+    write_dictionary_pool_info(pool_info);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn set_pool_allowlist() -> Result<(), Error> {
+    let current_contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let users: Vec<Key> = runtime::get_named_arg(ARG_USERS);
+    let allowed: bool = runtime::get_named_arg(ARG_ALLOWED);
+    for user in users {
+        limits::set_allowed_staker(pool_id, user, allowed);
+    }
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn is_allowed_staker() {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let user: Key =
+        helpers::get_named_arg_with_user_errors(ARG_USER, Error::MissingUser, Error::InvalidUser)
+            .unwrap_or_revert();
+
+    let return_value = CLValue::from_t(limits::is_allowed_staker(pool_id, user))
+        .unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Rewards emitted for all pools over [from, to], following the emission schedule
+fn get_multiplier(from: U256, to: U256) -> U256 {
+    emissions::get_emission(from, to)
+}
+fn update_pool(rewards_token: Key, pool_id: u64) {
+    // get Pool_Info of this pool
+
+    let mut this_pool = get_pool_info(pool_id);
+
+    // get current block stamps
+    let current_block_timestamps: U256 = U256::from(current_block_timestamp());
+
+    // Already up to date, e.g. a second call in the same block
+    if current_block_timestamps <= this_pool.last_reward_second {
+        return;
+    }
+
+    streams::update_reward_streams(&mut this_pool, current_block_timestamps);
+
+    // Todo: update pool
+    let multiplier: U256 = get_multiplier(this_pool.last_reward_second, current_block_timestamps);
+    let total_alloc_point: U256 = helpers::get_stored_value_with_user_errors::<U256>(
+        TOTAL_ALLOC_POINT,
+        Error::MissingTotalAllocPoint,
+        Error::InvalidTotalAllocPoint,
+    );
+
+    if total_alloc_point > U256::from("0") && this_pool.boosted_supply != U256::from("0") {
+        let reward: U256 = multiplier * this_pool.alloc_point / total_alloc_point;
+        // Todo: transfer reward to this contract
+
+        let contract_key: Key = get_self_key();
+
+        // mint reward
+
+        // mint_reward_to_contract(contract_key, reward);
 
         // let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
         //     REWARD_TOKEN,
         //     Error::MissingRewardToken,
         //     Error::InvalidRewardToken,
         // );
-        let contract_hash_addr: HashAddr = rewards_token.into_hash().unwrap_or_revert();
-        let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
-        let _: () = runtime::call_contract(
-            contract_hash,
-            MINT_ENTRY_POINT_NAME,
-            runtime_args! {
-                "owner" => contract_key,
-                "amount" => reward,
-            },
-        );
+        // Funded rewards are already held by this contract
+        if !emissions::is_funded() {
+            let contract_hash_addr: HashAddr = rewards_token.into_hash().unwrap_or_revert();
+            let contract_hash: ContractHash = ContractHash::new(contract_hash_addr);
+            let _: () = runtime::call_contract(
+                contract_hash,
+                MINT_ENTRY_POINT_NAME,
+                runtime_args! {
+                    "owner" => contract_key,
+                    "amount" => reward,
+                },
+            );
+        }
         //update
         let new_acc_reward_per_share = this_pool.acc_reward_per_share.clone()
-            + (reward * U256::from(u256_10_18) / this_pool.lp_supply);
+            + (reward * U256::from(u256_10_18) / this_pool.boosted_supply);
 
         let new_this_pool = PoolInfo {
             pool_id: pool_id.clone(),
@@ -880,19 +1663,31 @@ fn update_pool(rewards_token: Key, pool_id: u64) {
             alloc_point: this_pool.alloc_point,
             last_reward_second: current_block_timestamps.clone(),
             acc_reward_per_share: new_acc_reward_per_share,
-            //  min_stake_duration: this_pool.min_stake_duration,
-            //  early_withdraw_penalty_rate: this_pool.early_withdraw_penalty_rate,
+            min_stake_duration: this_pool.min_stake_duration,
+            early_withdraw_penalty_rate: this_pool.early_withdraw_penalty_rate,
             lp_supply: this_pool.lp_supply,
+            boosted_supply: this_pool.boosted_supply,
+            reward_streams: this_pool.reward_streams,
+            deposit_fee_rate: this_pool.deposit_fee_rate,
+            withdraw_fee_rate: this_pool.withdraw_fee_rate,
+            max_pool_stake: this_pool.max_pool_stake,
+            max_user_stake: this_pool.max_user_stake,
+            private_pool: this_pool.private_pool,
         };
 
         // save pool_info
 
-        write_dictionary_value_from_key(
-            POOL_INFO,
-            &pool_id.to_string(),
-            // this_pool,
-            casper_serde_json_wasm::to_string_pretty(&new_this_pool).unwrap(),
-        );
+        write_dictionary_pool_info(new_this_pool);
+    } else {
+        // Nothing staked, skip the elapsed time so it isn't paid to the next staker. A funded
+        // budget keeps the pool's share for the next notify_reward_amount.
+        if emissions::is_funded() && total_alloc_point > U256::from("0") {
+            emissions::add_undistributed_rewards(
+                multiplier * this_pool.alloc_point / total_alloc_point,
+            );
+        }
+        this_pool.last_reward_second = current_block_timestamps;
+        write_dictionary_pool_info(this_pool);
     }
 }
 // View function to see pending rewards of user
@@ -920,12 +1715,6 @@ pub extern "C" fn get_pending_rewards2() -> U256 {
         Error::InvalidTotalAllocPoint,
     );
 
-    let reward_per_second: U256 = helpers::get_stored_value_with_user_errors(
-        REWARD_PER_SECOND,
-        Error::MissingRewardPerSecond,
-        Error::InvalidRewardPerSecond,
-    );
-
     let current_list_str: String = helpers::get_stored_value_with_user_errors(
         POOL_LIST,
         Error::MissingPoolList,
@@ -948,8 +1737,7 @@ pub extern "C" fn get_pending_rewards2() -> U256 {
     {
         let multiplier: U256 =
             get_multiplier(pool_info.last_reward_second, current_block_timestamp);
-        let rewards: U256 =
-            (multiplier * reward_per_second * pool_info.alloc_point) / total_alloc_point;
+        let rewards: U256 = (multiplier * pool_info.alloc_point) / total_alloc_point;
         acc_reward_per_share =
             acc_reward_per_share.clone() + (rewards * U256::from(u256_10_18)) / pool_info.lp_supply;
     }
@@ -986,35 +1774,22 @@ pub extern "C" fn get_pending_rewards() {
         Error::InvalidTotalAllocPoint,
     );
 
-    let reward_per_second: U256 = helpers::get_stored_value_with_user_errors(
-        REWARD_PER_SECOND,
-        Error::MissingRewardPerSecond,
-        Error::InvalidRewardPerSecond,
-    );
-
-    let pool_info_str: String =
-        helpers::get_dictionary_value_from_key(POOL_INFO, &pool_id.clone().to_string()).unwrap();
-    // if casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).is_err() {
-    //     runtime::revert(Error::CanNotGetPoolList)
-    // }
-
-    let pool_info = casper_serde_json_wasm::from_str::<PoolInfo>(&pool_info_str).unwrap();
+    let pool_info = get_pool_info(pool_id);
 
     let user_info_of_this_pool = get_user_info(pool_id, user);
     let mut acc_reward_per_share: U256 = pool_info.acc_reward_per_share;
     let current_block_timestamp = U256::from(current_block_timestamp());
     if current_block_timestamp > pool_info.last_reward_second
-        && pool_info.lp_supply != U256::from("0")
+        && pool_info.boosted_supply != U256::from("0")
         && total_alloc_point > U256::from("0")
     {
         let multiplier: U256 =
             get_multiplier(pool_info.last_reward_second, current_block_timestamp);
-        let rewards: U256 =
-            (multiplier * reward_per_second * pool_info.alloc_point) / total_alloc_point;
-        acc_reward_per_share =
-            acc_reward_per_share.clone() + (rewards * U256::from(u256_10_18)) / pool_info.lp_supply;
+        let rewards: U256 = (multiplier * pool_info.alloc_point) / total_alloc_point;
+        acc_reward_per_share = acc_reward_per_share.clone()
+            + (rewards * U256::from(u256_10_18)) / pool_info.boosted_supply;
     }
-    let maybe_return_value = ((user_info_of_this_pool.total_stake_amount * acc_reward_per_share)
+    let maybe_return_value = ((user_info_of_this_pool.boosted_amount * acc_reward_per_share)
         / U256::from(u256_10_18))
         - user_info_of_this_pool.reward_debt
         + user_info_of_this_pool.pending_rewards;
@@ -1024,6 +1799,179 @@ pub extern "C" fn get_pending_rewards() {
     runtime::ret(return_value)
 }
 
+// View function to see how much lp_token a user has staked in a pool
+#[no_mangle]
+pub extern "C" fn get_user_stake() {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let user: Key =
+        helpers::get_named_arg_with_user_errors(ARG_USER, Error::MissingUser, Error::InvalidUser)
+            .unwrap_or_revert();
+
+    let user_info_of_this_pool = get_user_info(pool_id, user);
+
+    let return_value = CLValue::from_t(user_info_of_this_pool.total_stake_amount)
+        .unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Lock state of a user in a pool: tier, end of the lock and boosted share
+#[no_mangle]
+pub extern "C" fn get_user_lock() {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let user: Key =
+        helpers::get_named_arg_with_user_errors(ARG_USER, Error::MissingUser, Error::InvalidUser)
+            .unwrap_or_revert();
+
+    let user_info_of_this_pool = get_user_info(pool_id, user);
+
+    let return_value = CLValue::from_t((
+        user_info_of_this_pool.lock_tier,
+        user_info_of_this_pool.locked_until,
+        user_info_of_this_pool.boosted_amount,
+    ))
+    .unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Stake of each user in a pool that is still locked, in the order of the users. Stake without a
+// running lock counts as zero, the market grants its fee discounts on this.
+#[no_mangle]
+pub extern "C" fn get_locked_stakes() {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let users: Vec<Key> = runtime::get_named_arg(ARG_USERS);
+    let now = U256::from(current_block_timestamp());
+
+    let locked_stakes: Vec<U256> = users
+        .iter()
+        .map(|user| {
+            let user_info_of_this_pool = get_user_info(pool_id, *user);
+            if user_info_of_this_pool.locked_until > now {
+                user_info_of_this_pool.total_stake_amount
+            } else {
+                U256::zero()
+            }
+        })
+        .collect();
+
+    let return_value = CLValue::from_t(locked_stakes)
+        .unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// View function to see the pending rewards of a user in every reward stream of a pool, in the
+// order of the streams
+#[no_mangle]
+pub extern "C" fn get_pending_stream_rewards() {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let user: Key =
+        helpers::get_named_arg_with_user_errors(ARG_USER, Error::MissingUser, Error::InvalidUser)
+            .unwrap_or_revert();
+
+    let mut pool_info = get_pool_info(pool_id);
+    let mut user_info_of_this_pool = get_user_info(pool_id, user);
+    streams::update_reward_streams(&mut pool_info, U256::from(current_block_timestamp()));
+    streams::accrue_stream_rewards(&mut user_info_of_this_pool, &pool_info);
+
+    let return_value = CLValue::from_t(user_info_of_this_pool.stream_pending_rewards)
+        .unwrap_or_revert_with(Error::FailedToConvertToCLValue);
+    runtime::ret(return_value)
+}
+
+// Drops the boost of a lock that has ended, anyone can call it so expired boosts don't keep
+// diluting the other stakers. Rewards earned so far stay pending for the user.
+#[no_mangle]
+pub extern "C" fn expire_lock() -> Result<(), Error> {
+    let pool_id: u64 = runtime::get_named_arg(ARG_POOL_ID);
+    let user: Key = runtime::get_named_arg(ARG_USER);
+
+    let mut user_info_of_this_pool = get_user_info(pool_id, user);
+    let now = U256::from(current_block_timestamp());
+    if user_info_of_this_pool.locked_until > now
+        || user_info_of_this_pool.lock_multiplier <= U256::from(locks::BPS_DIVISOR)
+    {
+        runtime::revert(Error::LockNotExpired);
+    }
+
+    let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
+        REWARD_TOKEN,
+        Error::MissingRewardToken,
+        Error::InvalidRewardToken,
+    );
+    update_pool(rewards_token, pool_id);
+    let mut pool_info = get_pool_info(pool_id);
+
+    let pending: U256 = user_info_of_this_pool.boosted_amount * pool_info.acc_reward_per_share
+        / U256::from(u256_10_18)
+        - user_info_of_this_pool.reward_debt;
+    user_info_of_this_pool.pending_rewards = user_info_of_this_pool.pending_rewards + pending;
+    streams::accrue_stream_rewards(&mut user_info_of_this_pool, &pool_info);
+    user_info_of_this_pool.lock_tier = 0;
+    user_info_of_this_pool.lock_multiplier = U256::from(locks::BPS_DIVISOR);
+    update_boosted_amount(&mut user_info_of_this_pool, &mut pool_info);
+
+    write_dictionary_user_info(pool_id, user, user_info_of_this_pool);
+    write_dictionary_pool_info(pool_info);
+
+    events::emit(&StakingEvent::LockExpired { user, pool_id });
+    Ok(())
+}
+
+// Rewrites a page of pools and user infos still stored as JSON strings with their bytesrepr
+// encoding. Already converted or unknown keys are skipped, so pages can be retried.
+#[no_mangle]
+pub extern "C" fn migrate_storage() -> Result<(), Error> {
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let pool_ids: Vec<u64> = runtime::get_named_arg(ARG_POOL_IDS);
+    for pool_id in pool_ids {
+        if let Some(pool_info) = get_legacy_pool_info(&pool_id.to_string()) {
+            write_dictionary_pool_info(pool_info);
+        }
+    }
+
+    let user_info_keys: Vec<String> = runtime::get_named_arg(ARG_USER_INFO_KEYS);
+    for user_info_key in user_info_keys {
+        if let Some(user_info) = get_legacy_user_info(&user_info_key) {
+            put_dictionary_value_from_key(USER_INFO, &user_info_key, user_info);
+        }
+    }
+
+    if runtime::get_key(TREASURY).is_none() {
+        set_key(TREASURY, current_contract_owner);
+    }
+    limits::create_allowlist_dictionary();
+    Ok(())
+}
+
 #[no_mangle]
 pub extern "C" fn transfer_owner() -> Result<(), Error> {
     let new_contract_owner: Key = runtime::get_named_arg(ARG_CONTRACT_OWNER);
@@ -1059,10 +2007,53 @@ pub extern "C" fn set_reward_token() -> Result<(), Error> {
     if caller != current_contract_owner {
         runtime::revert(Error::InvalidContractOwner);
     }
+    // A funded budget is held in the current token
+    emissions::require_minted_rewards();
+    // Rewards earned so far are minted in the current token
+    update_all_pools();
     set_key(REWARD_TOKEN, reward_token);
     Ok(())
 }
 
+// Lock tiers offered to stakers, tier n uses the n-th duration and multiplier. Stakes already
+// locked keep the multiplier they were locked with.
+#[no_mangle]
+pub extern "C" fn set_lock_tiers() -> Result<(), Error> {
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let durations: Vec<U256> = runtime::get_named_arg(ARG_LOCK_DURATIONS);
+    let multipliers: Vec<U256> = runtime::get_named_arg(ARG_LOCK_MULTIPLIERS);
+    locks::validate_lock_tiers(&durations, &multipliers);
+    set_key(LOCK_TIER_DURATIONS, durations);
+    set_key(LOCK_TIER_MULTIPLIERS, multipliers);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern "C" fn set_treasury() -> Result<(), Error> {
+    let treasury: Key = runtime::get_named_arg(ARG_TREASURY);
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+    set_key(TREASURY, treasury);
+    Ok(())
+}
+
+// Emission rate used while no emission schedule is set
 #[no_mangle]
 pub extern "C" fn set_reward_per_second() -> Result<(), Error> {
     // let current_contract_owner = runtime::get_key(CONTRACT_OWNER_KEY_NAME).unwrap_or_revert();
@@ -1079,10 +2070,172 @@ pub extern "C" fn set_reward_per_second() -> Result<(), Error> {
         runtime::revert(Error::InvalidContractOwner);
     }
     let new_reward_per_second: U256 = runtime::get_named_arg(ARG_REWARD_PER_SECOND);
+    emissions::require_minted_rewards();
+    update_all_pools();
     set_key(REWARD_PER_SECOND, new_reward_per_second);
     Ok(())
 }
 
+// Replaces the emission schedule, epochs that already started only apply from now on since every
+// pool is settled first. Empty epochs go back to the constant REWARD_PER_SECOND.
+#[no_mangle]
+pub extern "C" fn set_emission_schedule() -> Result<(), Error> {
+    let current_contract_owner = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let epoch_starts: Vec<U256> = runtime::get_named_arg(ARG_EPOCH_STARTS);
+    let epoch_rates: Vec<U256> = runtime::get_named_arg(ARG_EPOCH_RATES);
+    let emission_end: U256 = runtime::get_named_arg(ARG_EMISSION_END);
+    emissions::validate_emission_schedule(&epoch_starts, &epoch_rates, emission_end);
+    emissions::require_minted_rewards();
+
+    update_all_pools();
+    set_key(EMISSION_EPOCH_STARTS, epoch_starts);
+    set_key(EMISSION_EPOCH_RATES, epoch_rates);
+    set_key(EMISSION_END, emission_end);
+    Ok(())
+}
+
+// Deposits a reward budget from the owner and spends it, with what is left of the current budget,
+// over `duration`. From then on rewards are never minted, they stop when the budget is spent.
+#[no_mangle]
+pub extern "C" fn notify_reward_amount() -> Result<(), Error> {
+    let current_contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let amount: U256 = runtime::get_named_arg(ARG_AMOUNT);
+    let duration: U256 = runtime::get_named_arg(ARG_DURATION);
+    let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
+        REWARD_TOKEN,
+        Error::MissingRewardToken,
+        Error::InvalidRewardToken,
+    );
+
+    update_all_pools();
+    transfer_from_erc20_token(rewards_token, caller, get_self_key(), amount).unwrap_or_revert();
+
+    let now = U256::from(current_block_timestamp());
+    let reward_per_second = emissions::start_funded_period(now, amount, duration);
+
+    events::emit(&StakingEvent::RewardFunded {
+        amount: amount,
+        reward_per_second: reward_per_second,
+        period_finish: now + duration,
+    });
+    Ok(())
+}
+
+// Adds a reward stream in another token to a pool, funded by `distributor`
+#[no_mangle]
+pub extern "C" fn add_reward_stream() -> Result<(), Error> {
+    let current_contract_owner: Key = helpers::get_stored_value_with_user_errors(
+        CONTRACT_OWNER_KEY_NAME,
+        Error::MissingContractOwner,
+        Error::InvalidContractOwner,
+    );
+    let caller = get_immediate_caller_key();
+    if caller != current_contract_owner {
+        runtime::revert(Error::InvalidContractOwner);
+    }
+
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let reward_token: Key = runtime::get_named_arg(ARG_REWARD_TOKEN);
+    let distributor: Key = runtime::get_named_arg(ARG_DISTRIBUTOR);
+    let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
+        REWARD_TOKEN,
+        Error::MissingRewardToken,
+        Error::InvalidRewardToken,
+    );
+
+    // Streams are paid out of this contract's balance of their token, a token it also holds as
+    // rewards or stake would let a stream pay out funds that aren't its own
+    if reward_token == rewards_token {
+        runtime::revert(Error::InvalidStreamRewardToken);
+    }
+    let number_of_pool: u64 = helpers::get_stored_value_with_user_errors(
+        NUMBER_OF_POOL,
+        Error::MissingNumberOfPool,
+        Error::InvalidNumberOfPool,
+    );
+    for other_pool_id in 0..number_of_pool {
+        if get_pool_info(other_pool_id).lp_token == reward_token {
+            runtime::revert(Error::InvalidStreamRewardToken);
+        }
+    }
+
+    update_pool(rewards_token, pool_id);
+    let mut pool_info = get_pool_info(pool_id);
+    streams::add_reward_stream(
+        &mut pool_info,
+        reward_token,
+        distributor,
+        U256::from(current_block_timestamp()),
+    );
+    write_dictionary_pool_info(pool_info);
+    Ok(())
+}
+
+// Deposits a budget from the distributor of a stream and spends it, with what is left of the
+// current one, over `duration`
+#[no_mangle]
+pub extern "C" fn notify_stream_reward() -> Result<(), Error> {
+    let pool_id: u64 = helpers::get_named_arg_with_user_errors(
+        ARG_POOL_ID,
+        Error::MissingPoolId,
+        Error::InvalidPoolId,
+    )
+    .unwrap_or_revert();
+    let stream_index: u32 = runtime::get_named_arg(ARG_STREAM_INDEX);
+    let amount: U256 = runtime::get_named_arg(ARG_AMOUNT);
+    let duration: U256 = runtime::get_named_arg(ARG_DURATION);
+    let rewards_token: Key = helpers::get_stored_value_with_user_errors::<Key>(
+        REWARD_TOKEN,
+        Error::MissingRewardToken,
+        Error::InvalidRewardToken,
+    );
+    let caller = get_immediate_caller_key();
+
+    update_pool(rewards_token, pool_id);
+    let mut pool_info = get_pool_info(pool_id);
+    let now = U256::from(current_block_timestamp());
+    let stream = streams::get_reward_stream(&mut pool_info, stream_index);
+    if caller != stream.distributor {
+        runtime::revert(Error::InvalidStreamDistributor);
+    }
+    let reward_token = stream.reward_token;
+    transfer_from_erc20_token(reward_token, caller, get_self_key(), amount).unwrap_or_revert();
+    let reward_per_second = streams::start_stream_period(stream, now, amount, duration);
+    write_dictionary_pool_info(pool_info);
+
+    events::emit(&StakingEvent::RewardStreamFunded {
+        pool_id: pool_id,
+        reward_token: reward_token,
+        amount: amount,
+        reward_per_second: reward_per_second,
+        period_finish: now + duration,
+    });
+    Ok(())
+}
+
 fn transfer_from_erc20_token(
     token: Key,
     source: Key,
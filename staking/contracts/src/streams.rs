@@ -0,0 +1,190 @@
+//! Extra reward streams of the staking pools.
+//!
+//! Next to the CAFI emission a pool can pay up to `MAX_REWARD_STREAMS` other tokens, for partner
+//! projects co-incentivizing it. Each stream is funded by its distributor with
+//! `notify_stream_reward`, which spreads the deposit over a duration, and is shared by boosted
+//! stake like the CAFI rewards. Streams never mint and stop when their period finishes. What a
+//! stream would have paid while nothing was staked is added to its next funding.
+use alloc::vec::Vec;
+use casper_contract::contract_api::runtime;
+use casper_types::{
+    bytesrepr::{self, FromBytes, ToBytes},
+    Key, U256,
+};
+use core::cmp::min;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::{u256_10_18, PoolInfo, UserInfo};
+
+pub const MAX_REWARD_STREAMS: usize = 4;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct RewardStream {
+    pub reward_token: Key,
+    pub distributor: Key, // the only account that can fund the stream
+    pub reward_per_second: U256,
+    pub period_finish: U256,
+    pub last_reward_second: U256,
+    pub acc_reward_per_share: U256, // per boosted share
+    #[serde(default)]
+    pub undistributed: U256, // paid while nothing was staked, added to the next funding
+}
+
+impl ToBytes for RewardStream {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        let mut result = bytesrepr::allocate_buffer(self)?;
+        result.append(&mut self.reward_token.to_bytes()?);
+        result.append(&mut self.distributor.to_bytes()?);
+        result.append(&mut self.reward_per_second.to_bytes()?);
+        result.append(&mut self.period_finish.to_bytes()?);
+        result.append(&mut self.last_reward_second.to_bytes()?);
+        result.append(&mut self.acc_reward_per_share.to_bytes()?);
+        result.append(&mut self.undistributed.to_bytes()?);
+        Ok(result)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.reward_token.serialized_length()
+            + self.distributor.serialized_length()
+            + self.reward_per_second.serialized_length()
+            + self.period_finish.serialized_length()
+            + self.last_reward_second.serialized_length()
+            + self.acc_reward_per_share.serialized_length()
+            + self.undistributed.serialized_length()
+    }
+}
+
+impl FromBytes for RewardStream {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (reward_token, remainder) = Key::from_bytes(bytes)?;
+        let (distributor, remainder) = Key::from_bytes(remainder)?;
+        let (reward_per_second, remainder) = U256::from_bytes(remainder)?;
+        let (period_finish, remainder) = U256::from_bytes(remainder)?;
+        let (last_reward_second, remainder) = U256::from_bytes(remainder)?;
+        let (acc_reward_per_share, remainder) = U256::from_bytes(remainder)?;
+        let (undistributed, remainder) = U256::from_bytes(remainder)?;
+        Ok((
+            RewardStream {
+                reward_token,
+                distributor,
+                reward_per_second,
+                period_finish,
+                last_reward_second,
+                acc_reward_per_share,
+                undistributed,
+            },
+            remainder,
+        ))
+    }
+}
+
+pub(crate) fn add_reward_stream(
+    pool_info: &mut PoolInfo,
+    reward_token: Key,
+    distributor: Key,
+    now: U256,
+) {
+    if pool_info.reward_streams.len() >= MAX_REWARD_STREAMS {
+        runtime::revert(Error::TooManyRewardStreams);
+    }
+    if pool_info
+        .reward_streams
+        .iter()
+        .any(|stream| stream.reward_token == reward_token)
+    {
+        runtime::revert(Error::RewardStreamExists);
+    }
+    pool_info.reward_streams.push(RewardStream {
+        reward_token,
+        distributor,
+        reward_per_second: U256::zero(),
+        period_finish: now,
+        last_reward_second: now,
+        acc_reward_per_share: U256::zero(),
+        undistributed: U256::zero(),
+    });
+}
+
+pub(crate) fn get_reward_stream(pool_info: &mut PoolInfo, stream_index: u32) -> &mut RewardStream {
+    match pool_info.reward_streams.get_mut(stream_index as usize) {
+        Some(stream) => stream,
+        None => runtime::revert(Error::InvalidRewardStream),
+    }
+}
+
+/// Accrues every stream of a pool up to `now`, or to the end of its period.
+pub(crate) fn update_reward_streams(pool_info: &mut PoolInfo, now: U256) {
+    let boosted_supply = pool_info.boosted_supply;
+    for stream in pool_info.reward_streams.iter_mut() {
+        let to = min(now, stream.period_finish);
+        if to <= stream.last_reward_second {
+            continue;
+        }
+        let reward = (to - stream.last_reward_second) * stream.reward_per_second;
+        if boosted_supply != U256::zero() {
+            stream.acc_reward_per_share += reward * U256::from(u256_10_18) / boosted_supply;
+        } else {
+            // Nothing staked, the elapsed time isn't paid to the next staker
+            stream.undistributed += reward;
+        }
+        stream.last_reward_second = to;
+    }
+}
+
+/// Spreads `amount`, plus what the running period hasn't paid yet and what nobody earned, evenly
+/// over `duration` from `now`. The stream must be up to date. Returns the new rate.
+pub(crate) fn start_stream_period(
+    stream: &mut RewardStream,
+    now: U256,
+    amount: U256,
+    duration: U256,
+) -> U256 {
+    if duration == U256::zero() {
+        runtime::revert(Error::InvalidRewardDuration);
+    }
+
+    let mut budget = amount + stream.undistributed;
+    if now < stream.period_finish {
+        budget += (stream.period_finish - now) * stream.reward_per_second;
+    }
+    let rate = budget / duration;
+    if rate == U256::zero() {
+        runtime::revert(Error::InvalidAmount);
+    }
+
+    stream.undistributed = U256::zero();
+    stream.reward_per_second = rate;
+    stream.last_reward_second = now;
+    stream.period_finish = now + duration;
+    rate
+}
+
+// Users staked before a stream was added have no entry for it yet
+fn fit_to_streams(values: &mut Vec<U256>, pool_info: &PoolInfo) {
+    values.resize(pool_info.reward_streams.len(), U256::zero());
+}
+
+/// Moves what a user earned from every stream to its pending rewards, before its boosted amount
+/// changes.
+pub(crate) fn accrue_stream_rewards(user_info: &mut UserInfo, pool_info: &PoolInfo) {
+    fit_to_streams(&mut user_info.stream_reward_debts, pool_info);
+    fit_to_streams(&mut user_info.stream_pending_rewards, pool_info);
+    for (index, stream) in pool_info.reward_streams.iter().enumerate() {
+        let earned = user_info.boosted_amount * stream.acc_reward_per_share
+            / U256::from(u256_10_18)
+            - user_info.stream_reward_debts[index];
+        user_info.stream_pending_rewards[index] += earned;
+        user_info.stream_reward_debts[index] += earned;
+    }
+}
+
+/// Settles the stream reward debts of a user on its current boosted amount.
+pub(crate) fn reset_stream_debts(user_info: &mut UserInfo, pool_info: &PoolInfo) {
+    fit_to_streams(&mut user_info.stream_reward_debts, pool_info);
+    fit_to_streams(&mut user_info.stream_pending_rewards, pool_info);
+    for (index, stream) in pool_info.reward_streams.iter().enumerate() {
+        user_info.stream_reward_debts[index] =
+            user_info.boosted_amount * stream.acc_reward_per_share / U256::from(u256_10_18);
+    }
+}
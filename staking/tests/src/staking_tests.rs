@@ -0,0 +1,747 @@
+use once_cell::sync::Lazy;
+
+use casper_engine_test_support::{
+    ExecuteRequestBuilder, InMemoryWasmTestBuilder, DEFAULT_ACCOUNT_ADDR,
+    DEFAULT_RUN_GENESIS_REQUEST, MINIMUM_ACCOUNT_CREATION_BALANCE,
+};
+use casper_execution_engine::core::{
+    engine_state::{Error as CoreError, ExecuteRequest},
+    execution::Error as ExecError,
+};
+use casper_types::{
+    account::AccountHash, runtime_args, system::mint, ApiError, ContractHash, ContractPackageHash,
+    Key, PublicKey, RuntimeArgs, SecretKey, U256,
+};
+
+const STAKING_CONTRACT_WASM: &str = "contract.wasm";
+const EXAMPLE_ERC20_TOKEN: &str = "erc20_token.wasm";
+const CONTRACT_ERC20_TEST_CALL: &str = "erc20_test_call.wasm";
+const ERC20_TOKEN_CONTRACT_KEY: &str = "erc20_token_contract";
+const ERC20_TEST_CALL_KEY: &str = "erc20_test_call";
+const RESULT_KEY: &str = "result";
+
+const STAKING_NAME: &str = "cafi_staking";
+const STAKING_PACKAGE_KEY: &str = "cafi_staking_package_name";
+
+const ARG_NAME: &str = "name";
+const ARG_SYMBOL: &str = "symbol";
+const ARG_DECIMALS: &str = "decimals";
+const ARG_TOTAL_SUPPLY: &str = "total_supply";
+const ARG_AMOUNT: &str = "amount";
+const ARG_RECIPIENT: &str = "recipient";
+const ARG_SPENDER: &str = "spender";
+const ARG_TOKEN_CONTRACT: &str = "token_contract";
+const ARG_ADDRESS: &str = "address";
+
+const ARG_STAKING_CONTRACT_NAME: &str = "staking_contract_name";
+const ARG_CONTRACT_OWNER: &str = "contract_owner";
+const ARG_REWARD_TOKEN: &str = "reward_token";
+const ARG_REWARD_PER_SECOND: &str = "reward_per_second";
+const ARG_START_SECOND: &str = "start_second";
+const ARG_LP_CONTRACT_HASH: &str = "lp_contract_hash";
+const ARG_ALLOC_POINT: &str = "alloc_point";
+const ARG_LAST_REWARD_SECOND: &str = "last_reward_second";
+const ARG_ACC_REWARD_PER_SHARE: &str = "acc_reward_per_share";
+const ARG_PENALTY_RATE: &str = "penalty_rate";
+const ARG_POOL_ID: &str = "pool_id";
+const ARG_LOCK_TIER: &str = "lock_tier";
+const ARG_LOCK_DURATIONS: &str = "lock_durations";
+const ARG_LOCK_MULTIPLIERS: &str = "lock_multipliers";
+const ARG_TREASURY: &str = "treasury";
+const ARG_DISTRIBUTOR: &str = "distributor";
+const ARG_STREAM_INDEX: &str = "stream_index";
+const ARG_DURATION: &str = "duration";
+
+const METHOD_TRANSFER: &str = "transfer";
+const METHOD_APPROVE: &str = "approve";
+const CHECK_BALANCE_OF_ENTRYPOINT: &str = "check_balance_of";
+const METHOD_ADD_NEW_POOL: &str = "add_new_pool";
+const METHOD_STAKE: &str = "stake";
+const METHOD_UN_STAKE: &str = "un_stake";
+const METHOD_EMERGENCY_WITHDRAW: &str = "emergency_withdraw";
+const METHOD_COMPOUND: &str = "compound";
+const METHOD_SET_REWARD_TOKEN: &str = "set_reward_token";
+const METHOD_HARVEST: &str = "harvest";
+const METHOD_ADD_REWARD_STREAM: &str = "add_reward_stream";
+const METHOD_NOTIFY_STREAM_REWARD: &str = "notify_stream_reward";
+const METHOD_SET_LOCK_TIERS: &str = "set_lock_tiers";
+const METHOD_SET_TREASURY: &str = "set_treasury";
+
+const ERROR_INVALID_AMOUNT: u16 = 110;
+const ERROR_STAKE_LOCKED: u16 = 129;
+const ERROR_INVALID_STREAM_REWARD_TOKEN: u16 = 149;
+
+const TOKEN_TOTAL_SUPPLY: u64 = 1_000_000_000;
+const STAKER_BALANCE: u64 = 1_000_000;
+const STAKE_AMOUNT: u64 = 10_000;
+// Block times are in milliseconds
+const START_TIME: u64 = 1_000;
+const LOCK_DURATION: u64 = 100_000;
+const LOCK_MULTIPLIER: u64 = 20_000;
+const PENALTY_RATE: u64 = 1_000;
+
+static ACCOUNT_1_SECRET_KEY: Lazy<SecretKey> =
+    Lazy::new(|| SecretKey::secp256k1_from_bytes(&[221u8; 32]).unwrap());
+static ACCOUNT_1_PUBLIC_KEY: Lazy<PublicKey> =
+    Lazy::new(|| PublicKey::from(&*ACCOUNT_1_SECRET_KEY));
+static ACCOUNT_1_ADDR: Lazy<AccountHash> = Lazy::new(|| ACCOUNT_1_PUBLIC_KEY.to_account_hash());
+
+static ACCOUNT_2_SECRET_KEY: Lazy<SecretKey> =
+    Lazy::new(|| SecretKey::secp256k1_from_bytes(&[212u8; 32]).unwrap());
+static ACCOUNT_2_PUBLIC_KEY: Lazy<PublicKey> =
+    Lazy::new(|| PublicKey::from(&*ACCOUNT_2_SECRET_KEY));
+static ACCOUNT_2_ADDR: Lazy<AccountHash> = Lazy::new(|| ACCOUNT_2_PUBLIC_KEY.to_account_hash());
+
+#[derive(Copy, Clone)]
+struct TestContext {
+    staking: ContractHash,
+    staking_package: ContractPackageHash,
+    lp_token: ContractHash,
+    reward_token: ContractHash,
+    stream_token: ContractHash,
+    erc20_test_call: ContractPackageHash,
+}
+
+fn named_key_hash(builder: &InMemoryWasmTestBuilder, name: &str) -> [u8; 32] {
+    let account = builder
+        .get_account(*DEFAULT_ACCOUNT_ADDR)
+        .expect("should have account");
+    account
+        .named_keys()
+        .get(name)
+        .and_then(|key| key.into_hash())
+        .expect("should have hash")
+}
+
+fn install_erc20_token(builder: &mut InMemoryWasmTestBuilder, symbol: &str) -> ContractHash {
+    let install_request = ExecuteRequestBuilder::standard(
+        *DEFAULT_ACCOUNT_ADDR,
+        EXAMPLE_ERC20_TOKEN,
+        runtime_args! {
+            ARG_NAME => symbol,
+            ARG_SYMBOL => symbol,
+            ARG_DECIMALS => 9u8,
+            ARG_TOTAL_SUPPLY => U256::from(TOKEN_TOTAL_SUPPLY),
+        },
+    )
+    .build();
+    builder.exec(install_request).expect_success().commit();
+
+    ContractHash::new(named_key_hash(builder, ERC20_TOKEN_CONTRACT_KEY))
+}
+
+fn setup() -> (InMemoryWasmTestBuilder, TestContext) {
+    let mut builder = InMemoryWasmTestBuilder::default();
+    builder.run_genesis(&*DEFAULT_RUN_GENESIS_REQUEST);
+
+    let id: Option<u64> = None;
+    for account in [*ACCOUNT_1_ADDR, *ACCOUNT_2_ADDR] {
+        let transfer_args = runtime_args! {
+            mint::ARG_TARGET => account,
+            mint::ARG_AMOUNT => MINIMUM_ACCOUNT_CREATION_BALANCE,
+            mint::ARG_ID => id,
+        };
+        let transfer_request =
+            ExecuteRequestBuilder::transfer(*DEFAULT_ACCOUNT_ADDR, transfer_args).build();
+        builder.exec(transfer_request).expect_success().commit();
+    }
+
+    let lp_token = install_erc20_token(&mut builder, "LP");
+    let reward_token = install_erc20_token(&mut builder, "CAFI");
+    let stream_token = install_erc20_token(&mut builder, "PART");
+
+    let install_test_call_request = ExecuteRequestBuilder::standard(
+        *DEFAULT_ACCOUNT_ADDR,
+        CONTRACT_ERC20_TEST_CALL,
+        RuntimeArgs::default(),
+    )
+    .build();
+    builder
+        .exec(install_test_call_request)
+        .expect_success()
+        .commit();
+
+    let install_staking_request = ExecuteRequestBuilder::standard(
+        *DEFAULT_ACCOUNT_ADDR,
+        STAKING_CONTRACT_WASM,
+        runtime_args! {
+            ARG_STAKING_CONTRACT_NAME => STAKING_NAME,
+            ARG_CONTRACT_OWNER => Key::Account(*DEFAULT_ACCOUNT_ADDR),
+            ARG_REWARD_TOKEN => Key::from(reward_token),
+            ARG_REWARD_PER_SECOND => U256::one(),
+            ARG_START_SECOND => U256::zero(),
+        },
+    )
+    .build();
+    builder
+        .exec(install_staking_request)
+        .expect_success()
+        .commit();
+
+    let test_context = TestContext {
+        staking: ContractHash::new(named_key_hash(&builder, STAKING_NAME)),
+        staking_package: ContractPackageHash::new(named_key_hash(&builder, STAKING_PACKAGE_KEY)),
+        lp_token,
+        reward_token,
+        stream_token,
+        erc20_test_call: ContractPackageHash::new(named_key_hash(&builder, ERC20_TEST_CALL_KEY)),
+    };
+
+    // The penalties go to a treasury of their own so its balance only counts them
+    builder
+        .exec(call_staking(
+            &test_context,
+            *DEFAULT_ACCOUNT_ADDR,
+            METHOD_SET_TREASURY,
+            runtime_args! {
+                ARG_TREASURY => Key::Account(*ACCOUNT_2_ADDR),
+            },
+            START_TIME,
+        ))
+        .expect_success()
+        .commit();
+    builder
+        .exec(call_staking(
+            &test_context,
+            *DEFAULT_ACCOUNT_ADDR,
+            METHOD_SET_LOCK_TIERS,
+            runtime_args! {
+                ARG_LOCK_DURATIONS => vec![U256::from(LOCK_DURATION)],
+                ARG_LOCK_MULTIPLIERS => vec![U256::from(LOCK_MULTIPLIER)],
+            },
+            START_TIME,
+        ))
+        .expect_success()
+        .commit();
+    erc20_transfer(
+        &mut builder,
+        lp_token,
+        *DEFAULT_ACCOUNT_ADDR,
+        Key::Account(*ACCOUNT_1_ADDR),
+        U256::from(STAKER_BALANCE),
+    );
+
+    (builder, test_context)
+}
+
+fn call_staking(
+    test_context: &TestContext,
+    sender: AccountHash,
+    entry_point: &str,
+    args: RuntimeArgs,
+    block_time: u64,
+) -> ExecuteRequest {
+    ExecuteRequestBuilder::contract_call_by_hash(sender, test_context.staking, entry_point, args)
+        .with_block_time(block_time)
+        .build()
+}
+
+fn erc20_transfer(
+    builder: &mut InMemoryWasmTestBuilder,
+    token: ContractHash,
+    sender: AccountHash,
+    recipient: Key,
+    amount: U256,
+) {
+    let transfer_request = ExecuteRequestBuilder::contract_call_by_hash(
+        sender,
+        token,
+        METHOD_TRANSFER,
+        runtime_args! {
+            ARG_RECIPIENT => recipient,
+            ARG_AMOUNT => amount,
+        },
+    )
+    .build();
+    builder.exec(transfer_request).expect_success().commit();
+}
+
+fn erc20_approve_staking(
+    builder: &mut InMemoryWasmTestBuilder,
+    test_context: &TestContext,
+    token: ContractHash,
+    sender: AccountHash,
+    amount: U256,
+) {
+    // The staking contract moves tokens as its package
+    let approve_request = ExecuteRequestBuilder::contract_call_by_hash(
+        sender,
+        token,
+        METHOD_APPROVE,
+        runtime_args! {
+            ARG_SPENDER => Key::Hash(test_context.staking_package.value()),
+            ARG_AMOUNT => amount,
+        },
+    )
+    .build();
+    builder.exec(approve_request).expect_success().commit();
+}
+
+fn erc20_check_balance_of(
+    builder: &mut InMemoryWasmTestBuilder,
+    test_context: &TestContext,
+    token: ContractHash,
+    address: Key,
+) -> U256 {
+    let check_balance_args = runtime_args! {
+        ARG_TOKEN_CONTRACT => token,
+        ARG_ADDRESS => address,
+    };
+    let exec_request = ExecuteRequestBuilder::versioned_contract_call_by_hash(
+        *DEFAULT_ACCOUNT_ADDR,
+        test_context.erc20_test_call,
+        None,
+        CHECK_BALANCE_OF_ENTRYPOINT,
+        check_balance_args,
+    )
+    .build();
+    builder.exec(exec_request).expect_success().commit();
+
+    let contract_package = builder
+        .get_contract_package(test_context.erc20_test_call)
+        .expect("should have contract package");
+    let (_version, contract_hash) = contract_package
+        .enabled_versions()
+        .iter()
+        .rev()
+        .next()
+        .expect("should have latest version");
+    builder
+        .query(None, Key::from(*contract_hash), &[RESULT_KEY.to_string()])
+        .expect("should have result")
+        .as_cl_value()
+        .expect("should be CLValue")
+        .clone()
+        .into_t()
+        .expect("should be U256")
+}
+
+fn add_pool(
+    builder: &mut InMemoryWasmTestBuilder,
+    test_context: &TestContext,
+    lp_token: ContractHash,
+    penalty_rate: u64,
+) {
+    builder
+        .exec(call_staking(
+            test_context,
+            *DEFAULT_ACCOUNT_ADDR,
+            METHOD_ADD_NEW_POOL,
+            runtime_args! {
+                ARG_LP_CONTRACT_HASH => Key::from(lp_token),
+                ARG_ALLOC_POINT => U256::from(100),
+                ARG_LAST_REWARD_SECOND => U256::from(START_TIME),
+                ARG_ACC_REWARD_PER_SHARE => U256::zero(),
+                ARG_PENALTY_RATE => U256::from(penalty_rate),
+            },
+            START_TIME,
+        ))
+        .expect_success()
+        .commit();
+}
+
+fn stake(
+    builder: &mut InMemoryWasmTestBuilder,
+    test_context: &TestContext,
+    token: ContractHash,
+    pool_id: u64,
+    lock_tier: u8,
+) {
+    erc20_approve_staking(
+        builder,
+        test_context,
+        token,
+        *ACCOUNT_1_ADDR,
+        U256::from(STAKE_AMOUNT),
+    );
+    builder
+        .exec(call_staking(
+            test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_STAKE,
+            runtime_args! {
+                ARG_POOL_ID => pool_id,
+                ARG_AMOUNT => U256::from(STAKE_AMOUNT),
+                ARG_LOCK_TIER => lock_tier,
+            },
+            START_TIME,
+        ))
+        .expect_success()
+        .commit();
+}
+
+fn assert_user_error(builder: &InMemoryWasmTestBuilder, expected_error: u16) {
+    let error = builder.get_error().expect("should have error");
+    assert!(
+        matches!(error, CoreError::Exec(ExecError::Revert(ApiError::User(user_error))) if user_error == expected_error),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn should_charge_the_penalty_on_early_un_stake() {
+    let (mut builder, test_context) = setup();
+    add_pool(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        PENALTY_RATE,
+    );
+    stake(&mut builder, &test_context, test_context.lp_token, 0, 1);
+
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_UN_STAKE,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+                ARG_AMOUNT => U256::from(STAKE_AMOUNT),
+            },
+            START_TIME + LOCK_DURATION / 2,
+        ))
+        .expect_success()
+        .commit();
+
+    let penalty = U256::from(STAKE_AMOUNT * PENALTY_RATE / 10_000);
+    let staker_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Account(*ACCOUNT_1_ADDR),
+    );
+    let treasury_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Account(*ACCOUNT_2_ADDR),
+    );
+    assert_eq!(staker_balance, U256::from(STAKER_BALANCE) - penalty);
+    assert_eq!(treasury_balance, penalty);
+}
+
+#[test]
+fn should_not_charge_the_penalty_after_the_lock() {
+    let (mut builder, test_context) = setup();
+    add_pool(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        PENALTY_RATE,
+    );
+    stake(&mut builder, &test_context, test_context.lp_token, 0, 1);
+
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_UN_STAKE,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+                ARG_AMOUNT => U256::from(STAKE_AMOUNT),
+            },
+            START_TIME + LOCK_DURATION,
+        ))
+        .expect_success()
+        .commit();
+
+    let staker_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Account(*ACCOUNT_1_ADDR),
+    );
+    let treasury_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Account(*ACCOUNT_2_ADDR),
+    );
+    assert_eq!(staker_balance, U256::from(STAKER_BALANCE));
+    assert_eq!(treasury_balance, U256::zero());
+}
+
+#[test]
+fn should_keep_locked_stake_in_pools_without_penalty() {
+    let (mut builder, test_context) = setup();
+    add_pool(&mut builder, &test_context, test_context.lp_token, 0);
+    stake(&mut builder, &test_context, test_context.lp_token, 0, 1);
+
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_UN_STAKE,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+                ARG_AMOUNT => U256::from(STAKE_AMOUNT),
+            },
+            START_TIME + LOCK_DURATION / 2,
+        ))
+        .commit();
+
+    assert_user_error(&builder, ERROR_STAKE_LOCKED);
+}
+
+#[test]
+fn should_charge_the_penalty_on_early_emergency_withdraw() {
+    let (mut builder, test_context) = setup();
+    add_pool(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        PENALTY_RATE,
+    );
+    stake(&mut builder, &test_context, test_context.lp_token, 0, 1);
+
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_EMERGENCY_WITHDRAW,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+            },
+            START_TIME + LOCK_DURATION / 2,
+        ))
+        .expect_success()
+        .commit();
+
+    let penalty = U256::from(STAKE_AMOUNT * PENALTY_RATE / 10_000);
+    let staker_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Account(*ACCOUNT_1_ADDR),
+    );
+    let treasury_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Account(*ACCOUNT_2_ADDR),
+    );
+    assert_eq!(staker_balance, U256::from(STAKER_BALANCE) - penalty);
+    assert_eq!(treasury_balance, penalty);
+}
+
+#[test]
+fn should_not_emergency_withdraw_locked_stake_in_pools_without_penalty() {
+    let (mut builder, test_context) = setup();
+    add_pool(&mut builder, &test_context, test_context.lp_token, 0);
+    stake(&mut builder, &test_context, test_context.lp_token, 0, 1);
+
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_EMERGENCY_WITHDRAW,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+            },
+            START_TIME + LOCK_DURATION / 2,
+        ))
+        .commit();
+
+    assert_user_error(&builder, ERROR_STAKE_LOCKED);
+
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_EMERGENCY_WITHDRAW,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+            },
+            START_TIME + LOCK_DURATION,
+        ))
+        .expect_success()
+        .commit();
+
+    let staker_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Account(*ACCOUNT_1_ADDR),
+    );
+    assert_eq!(staker_balance, U256::from(STAKER_BALANCE));
+}
+
+#[test]
+fn should_compound_only_rewards_the_balance_covers() {
+    let (mut builder, test_context) = setup();
+    add_pool(&mut builder, &test_context, test_context.lp_token, 0);
+    stake(&mut builder, &test_context, test_context.lp_token, 0, 0);
+
+    // Rewards earned so far are paid in the previous token, the contract holds none of the new
+    // one besides the stake
+    let switch_time = START_TIME + 1_000;
+    builder
+        .exec(call_staking(
+            &test_context,
+            *DEFAULT_ACCOUNT_ADDR,
+            METHOD_SET_REWARD_TOKEN,
+            runtime_args! {
+                ARG_REWARD_TOKEN => Key::from(test_context.lp_token),
+            },
+            switch_time,
+        ))
+        .expect_success()
+        .commit();
+
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_COMPOUND,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+            },
+            switch_time,
+        ))
+        .commit();
+
+    assert_user_error(&builder, ERROR_INVALID_AMOUNT);
+
+    // Only the 500 minted since the switch are restaked, the 1_000 earned before stay pending
+    let compound_time = switch_time + 500;
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_COMPOUND,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+            },
+            compound_time,
+        ))
+        .expect_success()
+        .commit();
+
+    builder
+        .exec(call_staking(
+            &test_context,
+            *ACCOUNT_1_ADDR,
+            METHOD_EMERGENCY_WITHDRAW,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+            },
+            compound_time,
+        ))
+        .expect_success()
+        .commit();
+
+    let staker_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Account(*ACCOUNT_1_ADDR),
+    );
+    let contract_balance = erc20_check_balance_of(
+        &mut builder,
+        &test_context,
+        test_context.lp_token,
+        Key::Hash(test_context.staking_package.value()),
+    );
+    assert_eq!(staker_balance, U256::from(STAKER_BALANCE + 500));
+    assert_eq!(contract_balance, U256::zero());
+}
+
+fn add_reward_stream(test_context: &TestContext, reward_token: ContractHash) -> ExecuteRequest {
+    call_staking(
+        test_context,
+        *DEFAULT_ACCOUNT_ADDR,
+        METHOD_ADD_REWARD_STREAM,
+        runtime_args! {
+            ARG_POOL_ID => 0u64,
+            ARG_REWARD_TOKEN => Key::from(reward_token),
+            ARG_DISTRIBUTOR => Key::Account(*DEFAULT_ACCOUNT_ADDR),
+        },
+        START_TIME,
+    )
+}
+
+#[test]
+fn should_pay_stream_rewards_on_harvest() {
+    let (mut builder, test_context) = setup();
+    add_pool(&mut builder, &test_context, test_context.lp_token, 0);
+    stake(&mut builder, &test_context, test_context.lp_token, 0, 0);
+
+    builder
+        .exec(add_reward_stream(&test_context, test_context.stream_token))
+        .expect_success()
+        .commit();
+    let stream_amount = U256::from(STAKE_AMOUNT);
+    erc20_approve_staking(
+        &mut builder,
+        &test_context,
+        test_context.stream_token,
+        *DEFAULT_ACCOUNT_ADDR,
+        stream_amount,
+    );
+    builder
+        .exec(call_staking(
+            &test_context,
+            *DEFAULT_ACCOUNT_ADDR,
+            METHOD_NOTIFY_STREAM_REWARD,
+            runtime_args! {
+                ARG_POOL_ID => 0u64,
+                ARG_STREAM_INDEX => 0u32,
+                ARG_AMOUNT => stream_amount,
+                ARG_DURATION => U256::from(1_000),
+            },
+            START_TIME,
+        ))
+        .expect_success()
+        .commit();
+
+    // The stream has run its whole period, a later harvest has nothing more to pay
+    for harvest_time in [START_TIME + 1_000, START_TIME + 2_000] {
+        builder
+            .exec(call_staking(
+                &test_context,
+                *ACCOUNT_1_ADDR,
+                METHOD_HARVEST,
+                runtime_args! {
+                    ARG_POOL_ID => 0u64,
+                },
+                harvest_time,
+            ))
+            .expect_success()
+            .commit();
+
+        let staker_balance = erc20_check_balance_of(
+            &mut builder,
+            &test_context,
+            test_context.stream_token,
+            Key::Account(*ACCOUNT_1_ADDR),
+        );
+        assert_eq!(staker_balance, stream_amount);
+    }
+}
+
+#[test]
+fn should_not_stream_the_reward_token() {
+    let (mut builder, test_context) = setup();
+    add_pool(&mut builder, &test_context, test_context.lp_token, 0);
+
+    builder
+        .exec(add_reward_stream(&test_context, test_context.reward_token))
+        .commit();
+
+    assert_user_error(&builder, ERROR_INVALID_STREAM_REWARD_TOKEN);
+}
+
+#[test]
+fn should_not_stream_a_staked_token() {
+    let (mut builder, test_context) = setup();
+    add_pool(&mut builder, &test_context, test_context.lp_token, 0);
+    add_pool(&mut builder, &test_context, test_context.stream_token, 0);
+
+    // The token is staked in another pool than the one it would pay
+    builder
+        .exec(add_reward_stream(&test_context, test_context.stream_token))
+        .commit();
+
+    assert_user_error(&builder, ERROR_INVALID_STREAM_REWARD_TOKEN);
+}